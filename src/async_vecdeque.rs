@@ -0,0 +1,179 @@
+use crate::error::MmapVecDequeError;
+use crate::mmap_vecdeque::MmapVecDeque;
+use crate::reader::{Reader, Shared};
+use futures_core::Stream;
+use tokio::sync::{Mutex, MutexGuard};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::path::Path;
+use std::pin::{pin, Pin};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+/// Async front end of a `MmapVecDeque`, for use on a tokio runtime.
+///
+/// Elements are pushed and popped through `lock()`, whose guard gives synchronous access to the
+/// memory mappings. `commit()`, which syncs files to disk, runs on tokio's blocking thread pool
+/// and holds the lock until it completes; tasks waiting for the lock meanwhile yield instead of
+/// blocking their worker thread. Cloning is cheap; clones share the same deque.
+pub struct AsyncMmapVecDeque<T: Copy> {
+  inner: Arc<Mutex<MmapVecDeque<T>>>,
+  shared: Arc<Shared<T>>,
+}
+
+impl<T: Copy> Clone for AsyncMmapVecDeque<T> {
+  fn clone(&self) -> Self {
+    AsyncMmapVecDeque {
+      inner: self.inner.clone(),
+      shared: self.shared.clone(),
+    }
+  }
+}
+
+impl<T: Copy + Send + Sync + 'static> AsyncMmapVecDeque<T> {
+  /// Opens or creates the deque in `dir` on the blocking thread pool, like `MmapVecDeque::open_or_create`.
+  ///
+  /// # Safety
+  ///
+  /// See `MmapVecDeque::open_or_create`.
+  pub async unsafe fn open_or_create(dir: &Path, chunk_size: Option<usize>) -> Result<Self, MmapVecDequeError> {
+    let dir = dir.to_path_buf();
+    let deque = run_blocking(move || unsafe { MmapVecDeque::open_or_create(&dir, chunk_size) }).await?;
+    Ok(Self::new(deque))
+  }
+
+  pub fn new(deque: MmapVecDeque<T>) -> Self {
+    let shared = deque.shared().clone();
+    AsyncMmapVecDeque {
+      inner: Arc::new(Mutex::new(deque)),
+      shared,
+    }
+  }
+
+  /// Locks the deque for synchronous access, waiting for a running `commit()` to complete.
+  pub async fn lock(&self) -> MutexGuard<'_, MmapVecDeque<T>> {
+    self.inner.lock().await
+  }
+
+  /// Commits all changes on the blocking thread pool, like `MmapVecDeque::commit`.
+  pub async fn commit(&self) -> Result<(), MmapVecDequeError> {
+    let deque = self.inner.clone().lock_owned().await;
+    run_blocking(move || deque.commit()).await
+  }
+
+  /// Pops the front element, waiting for a `commit()` to add elements while the deque is empty.
+  pub async fn pop_front_wait(&self) -> Result<T, MmapVecDequeError> {
+    loop {
+      // Register for the next commit before looking, so that a commit in between isn't missed.
+      let mut committed = pin!(self.shared.committed_async.notified());
+      committed.as_mut().enable();
+      if let Some(val) = self.inner.lock().await.pop_front()? {
+        return Ok(val);
+      }
+      committed.await;
+    }
+  }
+
+  /// Returns a stream of the elements appended to the back by commits after this call.
+  ///
+  /// The stream reads committed elements through a `Reader`, so it doesn't consume them. Elements
+  /// popped from the front before the stream got to them are skipped.
+  pub async fn stream(&self) -> Result<CommittedStream<T>, MmapVecDequeError> {
+    let reader = self.inner.lock().await.reader()?;
+    let next = reader.range().1;
+    let tail = Tail { reader, shared: self.shared.clone(), next };
+    Ok(CommittedStream {
+      pending: Some(Box::pin(tail.next_batch())),
+      buffer: VecDeque::new(),
+      error: None,
+    })
+  }
+}
+
+/// `Stream` of newly committed elements, returned by `AsyncMmapVecDeque::stream`.
+///
+/// The stream ends if reading a commit fails; `error()` then returns the failure.
+pub struct CommittedStream<T: Copy> {
+  pending: Option<NextBatch<T>>, // `None` once the stream ended
+  buffer: VecDeque<T>,
+  error: Option<MmapVecDequeError>,
+}
+
+// No field is structurally pinned.
+impl<T: Copy> Unpin for CommittedStream<T> {}
+
+impl<T: Copy> CommittedStream<T> {
+  pub fn error(&self) -> Option<&MmapVecDequeError> {
+    self.error.as_ref()
+  }
+}
+
+impl<T: Copy + Send + Sync + 'static> Stream for CommittedStream<T> {
+  type Item = T;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+    let this = self.get_mut();
+    loop {
+      if let Some(val) = this.buffer.pop_front() {
+        return Poll::Ready(Some(val));
+      }
+      let Some(pending) = this.pending.as_mut() else {
+        return Poll::Ready(None);
+      };
+      let (tail, batch) = ready!(pending.as_mut().poll(cx));
+      match batch {
+        Ok(batch) => {
+          this.buffer.extend(batch);
+          this.pending = Some(Box::pin(tail.next_batch()));
+        }
+        Err(e) => {
+          this.error = Some(e);
+          this.pending = None;
+        }
+      }
+    }
+  }
+}
+
+type NextBatch<T> = Pin<Box<dyn Future<Output = (Tail<T>, Result<Vec<T>, MmapVecDequeError>)> + Send>>;
+
+/// Position of a `CommittedStream` in the deque.
+struct Tail<T: Copy> {
+  reader: Reader<T>,
+  shared: Arc<Shared<T>>,
+  next: u64, // Global index of the next element to yield
+}
+
+impl<T: Copy + Send + Sync + 'static> Tail<T> {
+  /// Waits for commits until there are elements past `next`, and returns them.
+  async fn next_batch(mut self) -> (Self, Result<Vec<T>, MmapVecDequeError>) {
+    let shared = self.shared.clone();
+    loop {
+      let mut committed = pin!(shared.committed_async.notified());
+      committed.as_mut().enable();
+      if let Err(e) = self.reader.refresh() {
+        return (self, Err(e));
+      }
+      let (start, end) = self.reader.range();
+      let from = self.next.max(start);
+      if from < end {
+        self.next = end;
+        let batch = self.reader.read_range(from, end);
+        return (self, Ok(batch));
+      }
+      committed.await;
+    }
+  }
+}
+
+async fn run_blocking<R, F>(f: F) -> Result<R, MmapVecDequeError>
+where
+  F: FnOnce() -> Result<R, MmapVecDequeError> + Send + 'static,
+  R: Send + 'static,
+{
+  match tokio::task::spawn_blocking(f).await {
+    Ok(result) => result,
+    Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+    Err(e) => Err(MmapVecDequeError::Other(format!("blocking task failed: {}", e))),
+  }
+}
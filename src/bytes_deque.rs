@@ -0,0 +1,335 @@
+use crate::error::MmapVecDequeError;
+use crate::mmap_vecdeque::{Iter, MmapVecDeque};
+use crate::options::DequeOptions;
+use crate::portable::Portable;
+use memmap2::{MmapMut, MmapOptions};
+use parking_lot::Mutex;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::iter::FusedIterator;
+use std::path::{Path, PathBuf};
+use std::slice;
+
+/// Size of a segment file. Records are appended until the next one doesn't fit, then a new
+/// segment is started; a record larger than this gets a segment of its own.
+const SEGMENT_SIZE: u64 = 1 << 20;
+
+/// Where a record lives: its segment file and the byte offset of its length prefix.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct RecordRef {
+  segment: u64,
+  offset: u64,
+}
+
+crate::persistent_type!(RecordRef, "mmap_vecdeque::RecordRef");
+
+impl Portable for RecordRef {
+  fn swap_bytes(self) -> Self {
+    RecordRef { segment: self.segment.swap_bytes(), offset: self.offset.swap_bytes() }
+  }
+}
+
+/// A durable deque of variable-length byte records, with the same commit and rollback semantics
+/// as `MmapVecDeque`. Reads return slices borrowed straight from the mapped files.
+///
+/// Each record is stored as a `u32` little-endian length followed by its bytes, appended to the
+/// memory-mapped `segment_{n}.bin` files in `dir`. The order of the deque is kept by an
+/// `MmapVecDeque` of record locations in `dir/index`, so a commit is as atomic as the index
+/// commit: records written since the last commit are flushed first and ignored if the index never
+/// points at them. Segments no committed record refers to any more are deleted by `commit()`.
+/// The index is portable (see `DequeOptions::portable`), so the directory can be copied to
+/// machines of another byte order or pointer width.
+pub struct MmapBytesDeque {
+  dir: PathBuf,
+  index: MmapVecDeque<RecordRef>,
+  segments: Mutex<HashMap<u64, MmapMut>>, // Mapped segment files
+  active: Mutex<Option<(u64, u64)>>, // Segment records are appended to, and where the next one goes
+  refs: Mutex<BTreeMap<u64, u64>>, // Number of records stored in each segment
+  unsynced: Mutex<HashSet<u64>>, // Segments written since the last commit
+  created: Mutex<HashMap<u64, File>>, // Segments created since the last commit, whose length isn't durable yet
+}
+
+impl MmapBytesDeque {
+  /// Opens the deque in `dir`, creating it if needed. `chunk_size` is the number of records per
+  /// chunk of the index, as for `MmapVecDeque::open_or_create`.
+  pub fn open_or_create(dir: &Path, chunk_size: Option<usize>) -> Result<Self, MmapVecDequeError> {
+    fs::create_dir_all(dir)?;
+    let mut options = DequeOptions::new();
+    options.persistent::<RecordRef>().portable::<RecordRef>();
+    if let Some(chunk_size) = chunk_size {
+      options.chunk_size(chunk_size);
+    }
+    // Safety: `RecordRef` is two `u64`s, so any bytes are a valid value.
+    let index = unsafe { options.open::<RecordRef>(&dir.join("index"))? };
+    let refs = count_refs(&index);
+
+    // Segments nothing refers to were released by a commit that died before deleting them, or
+    // only hold records of changes that were never committed.
+    let existing = list_segments(dir)?;
+    let last = existing.iter().chain(refs.keys()).copied().max();
+    for segment in existing {
+      if Some(segment) != last && !refs.contains_key(&segment) {
+        fs::remove_file(segment_file(dir, segment))?;
+      }
+    }
+
+    let deque = MmapBytesDeque {
+      dir: dir.to_path_buf(),
+      index,
+      segments: Mutex::new(HashMap::new()),
+      active: Mutex::new(None),
+      refs: Mutex::new(refs),
+      unsynced: Mutex::new(HashSet::new()),
+      created: Mutex::new(HashMap::new()),
+    };
+    // Appending resumes after the last record the index knows of; anything past it was never
+    // committed and is overwritten.
+    if let Some(last) = last {
+      let mut next = 0;
+      for record in deque.index.iter().filter(|record| record.segment == last) {
+        next = next.max(record.offset + 4 + deque.read(record)?.len() as u64);
+      }
+      *deque.active.lock() = Some((last, next));
+    }
+    Ok(deque)
+  }
+
+  /// Appends `bytes` as a record to the active segment, starting a new segment if it doesn't fit.
+  fn append(&self, bytes: &[u8]) -> Result<RecordRef, MmapVecDequeError> {
+    let len = u32::try_from(bytes.len()).map_err(|_| MmapVecDequeError::Other(format!("record of {} bytes is too large", bytes.len())))?;
+    let needed = 4 + bytes.len() as u64;
+
+    let mut active = self.active.lock();
+    let mut segments = self.segments.lock();
+    if let Some((segment, _)) = *active {
+      if let Entry::Vacant(entry) = segments.entry(segment) {
+        entry.insert(map_segment(&self.dir, segment)?);
+      }
+    }
+    let (segment, offset) = match *active {
+      Some((segment, next)) if next + needed <= segments[&segment].len() as u64 => (segment, next),
+      current => {
+        let segment = current.map_or(0, |(segment, _)| segment + 1);
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(segment_file(&self.dir, segment))?;
+        file.set_len(SEGMENT_SIZE.max(needed))?;
+        segments.insert(segment, map_segment(&self.dir, segment)?);
+        self.created.lock().insert(segment, file);
+        (segment, 0)
+      }
+    };
+    let mmap = segments.get_mut(&segment).expect("active segment is mapped");
+    let start = offset as usize;
+    mmap[start..start + 4].copy_from_slice(&len.to_le_bytes());
+    mmap[start + 4..start + 4 + bytes.len()].copy_from_slice(bytes);
+    *active = Some((segment, offset + needed));
+    drop(segments);
+    drop(active);
+
+    self.unsynced.lock().insert(segment);
+    Ok(RecordRef { segment, offset })
+  }
+
+  fn read(&self, record: RecordRef) -> Result<&[u8], MmapVecDequeError> {
+    let mut segments = self.segments.lock();
+    let mmap = match segments.entry(record.segment) {
+      Entry::Occupied(entry) => entry.into_mut(),
+      Entry::Vacant(entry) => entry.insert(map_segment(&self.dir, record.segment)?),
+    };
+    let out_of_bounds = || MmapVecDequeError::Other(format!("record at offset {} of segment {} is out of bounds", record.offset, record.segment));
+    let start = usize::try_from(record.offset).map_err(|_| out_of_bounds())?;
+    let prefix = mmap.get(start..start + 4).ok_or_else(out_of_bounds)?;
+    let len = u32::from_le_bytes(prefix.try_into().expect("prefix is 4 bytes")) as usize;
+    let bytes = mmap.get(start + 4..start + 4 + len).ok_or_else(out_of_bounds)?;
+    let (ptr, len) = (bytes.as_ptr(), bytes.len());
+    drop(segments);
+    // Safety: mappings are only dropped by `commit()`, which takes `&mut self`, and moving an
+    // `MmapMut` inside the map doesn't move the mapped memory.
+    Ok(unsafe { slice::from_raw_parts(ptr, len) })
+  }
+
+  fn add_ref(&self, record: RecordRef) {
+    *self.refs.lock().entry(record.segment).or_insert(0) += 1;
+  }
+
+  fn release(&self, record: RecordRef) {
+    let mut refs = self.refs.lock();
+    if let Some(count) = refs.get_mut(&record.segment) {
+      *count -= 1;
+      if *count == 0 {
+        refs.remove(&record.segment);
+      }
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.index.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.index.is_empty()
+  }
+
+  pub fn push_back(&mut self, bytes: &[u8]) -> Result<(), MmapVecDequeError> {
+    let record = self.append(bytes)?;
+    self.index.push_back(record)?;
+    self.add_ref(record);
+    Ok(())
+  }
+
+  pub fn push_front(&mut self, bytes: &[u8]) -> Result<(), MmapVecDequeError> {
+    let record = self.append(bytes)?;
+    self.index.push_front(record)?;
+    self.add_ref(record);
+    Ok(())
+  }
+
+  /// Removes the last record. Its bytes stay mapped until the next commit, so the returned slice
+  /// borrows the deque.
+  pub fn pop_back(&mut self) -> Result<Option<&[u8]>, MmapVecDequeError> {
+    let Some(record) = self.index.pop_back()? else {
+      return Ok(None);
+    };
+    self.release(record);
+    self.read(record).map(Some)
+  }
+
+  /// Removes the first record. Its bytes stay mapped until the next commit, so the returned slice
+  /// borrows the deque.
+  pub fn pop_front(&mut self) -> Result<Option<&[u8]>, MmapVecDequeError> {
+    let Some(record) = self.index.pop_front()? else {
+      return Ok(None);
+    };
+    self.release(record);
+    self.read(record).map(Some)
+  }
+
+  pub fn get(&self, index: usize) -> Result<Option<&[u8]>, MmapVecDequeError> {
+    self.index.get(index).map(|record| self.read(record)).transpose()
+  }
+
+  pub fn front(&self) -> Result<Option<&[u8]>, MmapVecDequeError> {
+    self.index.front().map(|record| self.read(record)).transpose()
+  }
+
+  pub fn back(&self) -> Result<Option<&[u8]>, MmapVecDequeError> {
+    self.index.back().map(|record| self.read(record)).transpose()
+  }
+
+  pub fn clear(&mut self) -> Result<(), MmapVecDequeError> {
+    self.index.clear()?;
+    self.refs.lock().clear();
+    Ok(())
+  }
+
+  pub fn iter(&self) -> BytesIter<'_> {
+    BytesIter { deque: self, inner: self.index.iter() }
+  }
+
+  /// Durably persists all changes since the last commit, then deletes the segments no record
+  /// refers to any more.
+  pub fn commit(&mut self) -> Result<(), MmapVecDequeError> {
+    let unsynced: Vec<u64> = self.unsynced.lock().drain().collect();
+    if !unsynced.is_empty() {
+      let segments = self.segments.lock();
+      for segment in unsynced {
+        if let Some(mmap) = segments.get(&segment) {
+          mmap.flush()?;
+        }
+      }
+      drop(segments);
+      // Segments created since the last commit must exist, at their full length, before the
+      // index refers to them.
+      for (_, file) in self.created.lock().drain() {
+        file.sync_all()?;
+      }
+      let dir_file = OpenOptions::new().read(true).open(&self.dir)?;
+      dir_file.sync_all()?;
+    }
+    self.index.commit()?;
+
+    let active = self.active.lock().map(|(segment, _)| segment);
+    let refs = self.refs.lock();
+    let mut segments = self.segments.lock();
+    for segment in list_segments(&self.dir)? {
+      if Some(segment) != active && !refs.contains_key(&segment) {
+        segments.remove(&segment);
+        fs::remove_file(segment_file(&self.dir, segment))?;
+      }
+    }
+    Ok(())
+  }
+
+  /// Discards all changes since the last commit. Records appended since then stay in their
+  /// segment until it is deleted.
+  pub fn rollback(&mut self) -> Result<(), MmapVecDequeError> {
+    self.index.rollback()?;
+    *self.refs.lock() = count_refs(&self.index);
+    Ok(())
+  }
+}
+
+fn count_refs(index: &MmapVecDeque<RecordRef>) -> BTreeMap<u64, u64> {
+  let mut refs = BTreeMap::new();
+  for record in index.iter() {
+    *refs.entry(record.segment).or_insert(0) += 1;
+  }
+  refs
+}
+
+fn segment_file(dir: &Path, segment: u64) -> PathBuf {
+  dir.join(format!("segment_{}.bin", segment))
+}
+
+/// Numbers of the segment files in `dir`.
+fn list_segments(dir: &Path) -> Result<Vec<u64>, MmapVecDequeError> {
+  let mut segments = Vec::new();
+  for entry in fs::read_dir(dir)? {
+    let segment = entry?
+      .file_name()
+      .to_str()
+      .and_then(|n| n.strip_prefix("segment_"))
+      .and_then(|n| n.strip_suffix(".bin"))
+      .and_then(|n| n.parse::<u64>().ok());
+    segments.extend(segment);
+  }
+  Ok(segments)
+}
+
+fn map_segment(dir: &Path, segment: u64) -> Result<MmapMut, MmapVecDequeError> {
+  let file = OpenOptions::new().read(true).write(true).open(segment_file(dir, segment))?;
+  Ok(unsafe { MmapOptions::new().map_mut(&file)? })
+}
+
+/// Iterator over the records of a `MmapBytesDeque`, borrowing each one from its mapping.
+pub struct BytesIter<'a> {
+  deque: &'a MmapBytesDeque,
+  inner: Iter<'a, RecordRef>,
+}
+
+impl<'a> Iterator for BytesIter<'a> {
+  type Item = Result<&'a [u8], MmapVecDequeError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.inner.next().map(|record| self.deque.read(record))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.inner.size_hint()
+  }
+
+  fn nth(&mut self, n: usize) -> Option<Self::Item> {
+    self.inner.nth(n).map(|record| self.deque.read(record))
+  }
+}
+
+impl DoubleEndedIterator for BytesIter<'_> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.inner.next_back().map(|record| self.deque.read(record))
+  }
+}
+
+impl ExactSizeIterator for BytesIter<'_> {}
+
+impl FusedIterator for BytesIter<'_> {}
@@ -1,7 +1,6 @@
 use thiserror::Error;
 use std::io;
-use postcard;
-use atomicwrites;
+use std::path::PathBuf;
 
 #[derive(Error, Debug)]
 pub enum MmapVecDequeError {
@@ -20,9 +19,18 @@ pub enum MmapVecDequeError {
   #[error("Type mismatch: stored type `{stored}`, requested type `{requested}`")]
   TypeMismatch { stored: String, requested: String },
 
+  #[error("Layout version mismatch: stored version `{stored}`, requested version `{requested}`")]
+  LayoutVersionMismatch { stored: u32, requested: u32 },
+
   #[error("Element size mismatch: stored size `{stored}`, requested size `{requested}`")]
   ElementSizeMismatch { stored: usize, requested: usize },
 
+  #[error("Element alignment mismatch: stored alignment `{stored}`, requested alignment `{requested}`")]
+  AlignmentMismatch { stored: usize, requested: usize },
+
+  #[error("Platform mismatch: the deque was written on a {stored} platform, this is a {current} platform")]
+  PlatformMismatch { stored: String, current: String },
+
   #[error("Zero-sized types are not supported")]
   ZeroSizedType,
 
@@ -32,6 +40,24 @@ pub enum MmapVecDequeError {
   #[error("Chunk size mismatch: stored size `{stored}`, requested size `{requested}`")]
   ChunkSizeMismatch { stored: usize, requested: usize },
 
+  #[error("Corruption detected in {}: {details}", match .chunk { Some(c) => format!("chunk {}", c), None => "metadata".to_string() })]
+  Corruption { chunk: Option<u64>, details: String },
+
+  #[error("Deque directory `{}` is locked by another handle", path.display())]
+  Locked { path: PathBuf },
+
+  #[error("Capacity mismatch: stored capacity `{stored:?}`, requested capacity `{requested:?}`")]
+  CapacityMismatch { stored: Option<u64>, requested: Option<u64> },
+
+  #[error("Deque is full: capacity is {capacity} elements")]
+  Full { capacity: u64 },
+
+  #[error("Disk quota exceeded: {needed} bytes needed, quota is {quota} bytes")]
+  QuotaExceeded { quota: u64, needed: u64 },
+
+  #[error("Unsupported format version {found}: this version of the crate reads formats up to {supported}")]
+  UnsupportedFormatVersion { found: u32, supported: u32 },
+
   #[error("Other error: {0}")]
   Other(String),
 }
@@ -0,0 +1,98 @@
+use crate::error::MmapVecDequeError;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+const JOURNAL_FILE: &str = "journal.bin";
+
+/// Rollback journal protecting committed slots that are overwritten before the next commit.
+///
+/// Layout: `u64` length of the saved `metadata.bin`, the saved `metadata.bin` bytes, then a
+/// sequence of `(u64 global index, element bytes)` records, all little-endian. Every record is
+/// synced before the slot it describes is overwritten, so a torn trailing record is never needed.
+pub(crate) struct Journal {
+  file: File,
+}
+
+/// Contents of a journal found on disk.
+pub(crate) struct JournalContents {
+  /// The saved `metadata.bin`; empty if the journal header itself was never completed.
+  pub(crate) metadata: Vec<u8>,
+  /// Original bytes of overwritten committed slots, keyed by global index.
+  pub(crate) records: Vec<(u64, Vec<u8>)>,
+}
+
+impl Journal {
+  pub(crate) fn path(dir: &Path) -> PathBuf {
+    dir.join(JOURNAL_FILE)
+  }
+
+  /// Creates a new journal holding `metadata` (the raw contents of the committed `metadata.bin`).
+  pub(crate) fn create(dir: &Path, metadata: &[u8]) -> Result<Self, MmapVecDequeError> {
+    let mut file = OpenOptions::new()
+      .write(true)
+      .create(true)
+      .truncate(true)
+      .open(Self::path(dir))?;
+    file.write_all(&(metadata.len() as u64).to_le_bytes())?;
+    file.write_all(metadata)?;
+    file.sync_all()?;
+    sync_dir(dir)?;
+    Ok(Journal { file })
+  }
+
+  /// Appends the original bytes of the element at `index`. Not durable until `sync()`.
+  pub(crate) fn append(&mut self, index: u64, bytes: &[u8]) -> Result<(), MmapVecDequeError> {
+    let mut record = Vec::with_capacity(8 + bytes.len());
+    record.extend_from_slice(&index.to_le_bytes());
+    record.extend_from_slice(bytes);
+    self.file.write_all(&record)?;
+    Ok(())
+  }
+
+  pub(crate) fn sync(&mut self) -> Result<(), MmapVecDequeError> {
+    self.file.sync_data()?;
+    Ok(())
+  }
+
+  /// Reads the journal in `dir`, if any, returning the saved metadata and the complete records.
+  pub(crate) fn read(dir: &Path, element_size: usize) -> Result<Option<JournalContents>, MmapVecDequeError> {
+    let data = match fs::read(Self::path(dir)) {
+      Ok(data) => data,
+      Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(e.into()),
+    };
+    let incomplete = JournalContents { metadata: Vec::new(), records: Vec::new() };
+    if data.len() < 8 {
+      return Ok(Some(incomplete));
+    }
+    let meta_len = u64::from_le_bytes(data[..8].try_into().unwrap()) as usize;
+    let Some(metadata) = data.get(8..8 + meta_len) else {
+      return Ok(Some(incomplete));
+    };
+
+    let mut records = Vec::new();
+    let record_size = 8 + element_size;
+    for record in data[8 + meta_len..].chunks_exact(record_size) {
+      let index = u64::from_le_bytes(record[..8].try_into().unwrap());
+      records.push((index, record[8..].to_vec()));
+    }
+    Ok(Some(JournalContents { metadata: metadata.to_vec(), records }))
+  }
+
+  /// Deletes the journal in `dir`. Once this returns, the journal can no longer be replayed.
+  pub(crate) fn remove(dir: &Path) -> Result<(), MmapVecDequeError> {
+    match fs::remove_file(Self::path(dir)) {
+      Ok(()) => {}
+      Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+      Err(e) => return Err(e.into()),
+    }
+    sync_dir(dir)
+  }
+}
+
+fn sync_dir(dir: &Path) -> Result<(), MmapVecDequeError> {
+  let dir_file = OpenOptions::new().read(true).open(dir)?;
+  dir_file.sync_all()?;
+  Ok(())
+}
@@ -5,10 +5,25 @@
 //! ## Key Features
 //!
 //! - **Deferred commits:** Changes are kept in memory until `commit()` is called.
-//! - **Atomic and durable commits:** Once `commit()` returns, all changes are atomically and durably persisted to disk.
+//! - **Atomic and durable commits:** Once `commit()` returns, all changes are atomically and durably persisted to disk. Committed elements overwritten before a commit are journaled, so a crash never exposes a half-applied commit.
+//! - **Rollback:** `rollback()` discards all changes made since the last `commit()`.
+//! - **Integrity checks:** `metadata.bin` and the committed contents of every chunk carry CRC32C checksums. Opening a deque verifies the first and last chunk, or every chunk with `DequeOptions::verify_on_open()`, and `verify()` checks them all on demand.
 //! - **Configurable chunk size:** Items are stored in fixed-size chunks of elements. By default, 10,000 elements per chunk.
+//! - **Ring-buffer mode:** `DequeOptions::bounded()` caps the length; pushing onto a full deque evicts from the opposite end or fails with `Full`, and the chunk files are reused circularly so disk usage stays constant.
+//! - **Disk quota:** `disk_usage()` reports the bytes the deque occupies, orphaned chunk files included, and `DequeOptions::quota()` makes pushes that would grow past a byte limit fail with `QuotaExceeded`.
+//! - **Directory locking:** An open deque holds an exclusive advisory lock on its directory, so two writers can never share it.
 //! - **Mmap-backed:** Data is accessed via memory mapping for potentially high performance.
-//! - **Iterators:** `iter()` and `iter_mut()` to traverse elements.
+//! - **Read-only access:** `open_read_only()` maps an existing deque read-only for inspection tools, even on read-only mounts, and `reader()` hands out cheaply clonable `Reader`s that other threads can use to scan the last commit while the deque keeps changing.
+//! - **Blocking pops:** `SharedDeque` shares a deque between threads, and its `pop_front_blocking()`, `pop_front_timeout()` and `pop_front_batch_blocking()` wait for another thread to commit new elements.
+//! - **Change notification:** `open_follower()` tails a deque written by another process, and `Reader::wait_for_commit()` wakes up as soon as the writer's `commit()` completes, through a futex word in a small shared `notify.bin` file.
+//! - **Async API:** With the `tokio` feature, `AsyncMmapVecDeque` runs commits on tokio's blocking thread pool and offers `pop_front_wait()` and a `Stream` of newly committed elements.
+//! - **Iterators:** `iter()`, `iter_mut()`, `range()` and `range_mut()` to traverse elements, and `chunks_slices()` to read them as contiguous slices of the mappings.
+//! - **Variable-length elements:** `MmapBytesDeque` stores opaque byte records in memory-mapped segment files and hands them out as slices borrowed from the mapping, and `MmapSerdeDeque` builds on it to store any `Serialize + DeserializeOwned` type, such as `String` or structs with heap data, as postcard records. Both keep the same push, pop, commit and rollback semantics.
+//! - **Sound element types:** Elements are read straight from file bytes, so any bytes must be a valid `T`. With the `bytemuck` feature, `open_pod()`, `open_read_only_pod()` and `open_follower_pod()` enforce this with a `bytemuck::Pod` bound; the constructors taking any `Copy` type, such as `open_or_create()`, are `unsafe` and leave it to the caller.
+//! - **Stable type identity:** Types implementing `PersistentType` (see `persistent_type!`) are stored under a name and layout version of your choosing instead of `std::any::type_name`, so toolchain upgrades and renames don't lock you out; `DequeOptions::accept_type_name()` adopts a deque stored under an older name.
+//! - **Schema migration:** `MmapVecDeque::migrate()` converts every element of a stored deque to a new element type, writing a new set of chunk files and switching to them with one atomic metadata write.
+//! - **Versioned format:** `metadata.bin` starts with a magic and format version, documented in FORMAT.md. Deques written by older releases are upgraded in place when opened, and formats newer than the crate are refused with `UnsupportedFormatVersion`.
+//! - **Portable format:** the metadata records the byte order, pointer width and element alignment, which are checked on open. Deques opened with `DequeOptions::portable()` are stored little-endian, so they can be copied between machines of different byte order; big-endian machines swap the bytes of each element on the fly.
 //!
 //! ## Usage
 //!
@@ -18,7 +33,8 @@
 //!
 //! fn main() -> Result<(), mmap_vecdeque::MmapVecDequeError> {
 //!   let dir = Path::new("my_deque_storage");
-//!   let mut deque = MmapVecDeque::<u64>::open_or_create(dir, None)?;
+//!   // Safety: any 8 bytes are a valid `u64`.
+//!   let mut deque = unsafe { MmapVecDeque::<u64>::open_or_create(dir, None) }?;
 //!
 //!   deque.push_back(42)?;
 //!   deque.push_front(1)?;
@@ -44,7 +60,29 @@
 //! }
 //! ```
 
+#[cfg(feature = "tokio")]
+mod async_vecdeque;
+mod bytes_deque;
 mod error;
+mod journal;
+mod lock;
+mod metadata;
 mod mmap_vecdeque;
-pub use mmap_vecdeque::{MmapVecDeque, Iter, IterMut};
+mod notify;
+mod options;
+mod persistent;
+mod portable;
+mod reader;
+mod serde_deque;
+mod shared_deque;
+pub use mmap_vecdeque::{MmapVecDeque, Iter, IterMut, Drain, ChunkSlices};
+pub use reader::{Reader, ReaderIter};
+pub use bytes_deque::{MmapBytesDeque, BytesIter};
+pub use serde_deque::{MmapSerdeDeque, SerdeIter};
+pub use shared_deque::{SharedDeque, SharedDequeGuard};
 pub use error::MmapVecDequeError;
+pub use options::{DequeOptions, Overflow};
+pub use persistent::PersistentType;
+pub use portable::Portable;
+#[cfg(feature = "tokio")]
+pub use async_vecdeque::{AsyncMmapVecDeque, CommittedStream};
@@ -0,0 +1,44 @@
+use crate::error::MmapVecDequeError;
+use fs4::fs_std::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::Path;
+
+const LOCK_FILE: &str = "lock";
+
+/// Advisory lock on a deque directory, held for as long as this value lives.
+///
+/// Writers take the lock exclusively and readers take it shared, so a writer excludes every other
+/// handle while any number of readers may coexist. The lock is released when the file is closed,
+/// including when the owning process dies.
+pub(crate) struct DirLock {
+  _file: File,
+}
+
+impl DirLock {
+  pub(crate) fn exclusive(dir: &Path) -> Result<Self, MmapVecDequeError> {
+    let path = dir.join(LOCK_FILE);
+    let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
+    Self::lock(dir, file, true)
+  }
+
+  /// Takes a shared lock without creating any file. Returns `None` when the directory has no
+  /// lock file, i.e. it was never opened by a writer of this version.
+  pub(crate) fn shared(dir: &Path) -> Result<Option<Self>, MmapVecDequeError> {
+    let file = match OpenOptions::new().read(true).open(dir.join(LOCK_FILE)) {
+      Ok(file) => file,
+      Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(e.into()),
+    };
+    Self::lock(dir, file, false).map(Some)
+  }
+
+  fn lock(dir: &Path, file: File, exclusive: bool) -> Result<Self, MmapVecDequeError> {
+    // Called through the trait, since newer `File`s have inherent methods of the same names.
+    let locked = if exclusive { FileExt::try_lock_exclusive(&file)? } else { FileExt::try_lock_shared(&file)? };
+    if !locked {
+      return Err(MmapVecDequeError::Locked { path: dir.to_path_buf() });
+    }
+    Ok(DirLock { _file: file })
+  }
+}
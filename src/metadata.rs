@@ -0,0 +1,264 @@
+use crate::error::MmapVecDequeError;
+use crate::options::{DequeOptions, Overflow};
+use serde::{Serialize, Deserialize};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::mem::{align_of, size_of};
+use std::path::{Path, PathBuf};
+
+/// First bytes of `metadata.bin`, followed by the format version. See FORMAT.md.
+const MAGIC: [u8; 4] = *b"MVDQ";
+
+/// Version of the on-disk format this crate writes.
+pub(crate) const FORMAT_VERSION: u32 = 1;
+
+/// Byte order and pointer width of this platform, recorded with every deque.
+pub(crate) const LITTLE_ENDIAN: bool = cfg!(target_endian = "little");
+pub(crate) const POINTER_WIDTH: u32 = usize::BITS;
+
+/// Largest element alignment supported. Chunk files are mapped at page boundaries, which are at
+/// least this aligned, and element sizes are multiples of their alignment, so every element in a
+/// mapping is properly aligned.
+const MAX_ALIGN: usize = 4096;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Metadata {
+  pub(crate) type_name: String, // `PersistentType::TYPE_NAME`, or `std::any::type_name` of other types
+  pub(crate) layout_version: u32,
+  pub(crate) element_size: usize,
+  pub(crate) chunk_size: usize,
+  pub(crate) start: u64,
+  pub(crate) end: u64,
+  pub(crate) checksums: Vec<u32>, // CRC32C of the committed elements in each live chunk, starting at the start chunk
+  pub(crate) capacity: Option<u64>, // Maximum length of a bounded deque
+  pub(crate) overflow: Overflow, // What pushing onto a full bounded deque does
+  pub(crate) generation: u64, // Incremented by `migrate`, which writes a new set of chunk files
+  pub(crate) little_endian: bool, // Byte order of the elements in the chunk files
+  pub(crate) pointer_width: u32, // Bits in a `usize` on the platform that created the deque
+  pub(crate) element_align: usize, // `align_of` the element type, or 0 if upgraded from version 0
+  pub(crate) portable: bool, // Elements have a fixed layout and can be converted to the other byte order
+}
+
+/// The metadata of deques written before the format header existed (format version 0), which had
+/// no checksums and no other settings.
+#[derive(Deserialize)]
+struct LegacyMetadata {
+  type_name: String,
+  element_size: usize,
+  chunk_size: usize,
+  start: u64,
+  end: u64,
+}
+
+impl Metadata {
+  /// Serializes the metadata behind the format header, followed by a CRC32C of everything before.
+  pub(crate) fn encode(&self) -> Result<Vec<u8>, MmapVecDequeError> {
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    postcard::to_io(self, &mut data)?;
+    let checksum = crc32c::crc32c(&data);
+    data.extend_from_slice(&checksum.to_le_bytes());
+    Ok(data)
+  }
+
+  pub(crate) fn decode(data: &[u8]) -> Result<Self, MmapVecDequeError> {
+    let Some(header) = data.strip_prefix(&MAGIC) else {
+      return Err(MmapVecDequeError::Corruption { chunk: None, details: "metadata has no format header".to_string() });
+    };
+    if header.len() < 8 {
+      return Err(MmapVecDequeError::Corruption { chunk: None, details: "metadata is truncated".to_string() });
+    }
+    // Checked first, since a newer format may also lay out the rest differently.
+    let version = u32::from_le_bytes(header[..4].try_into().unwrap());
+    if version > FORMAT_VERSION {
+      return Err(MmapVecDequeError::UnsupportedFormatVersion { found: version, supported: FORMAT_VERSION });
+    }
+    let (body, trailer) = data.split_at(data.len() - 4);
+    let expected = u32::from_le_bytes(trailer.try_into().unwrap());
+    let actual = crc32c::crc32c(body);
+    if expected != actual {
+      return Err(MmapVecDequeError::Corruption {
+        chunk: None,
+        details: format!("metadata checksum mismatch: expected {:08x}, found {:08x}", expected, actual),
+      });
+    }
+    Ok(postcard::from_bytes(&body[MAGIC.len() + 4..])?)
+  }
+
+  pub(crate) fn read(dir: &Path) -> Result<Self, MmapVecDequeError> {
+    Ok(Self::read_upgrading(dir)?.0)
+  }
+
+  /// Reads `metadata.bin`, upgrading older formats in memory. Returns whether it was upgraded, so
+  /// that writers can store the upgraded metadata.
+  pub(crate) fn read_upgrading(dir: &Path) -> Result<(Self, bool), MmapVecDequeError> {
+    let data = fs::read(dir.join("metadata.bin"))?;
+    if data.starts_with(&MAGIC) {
+      return Ok((Self::decode(&data)?, false));
+    }
+    let (legacy, rest) = postcard::take_from_bytes::<LegacyMetadata>(&data)?;
+    if !rest.is_empty() {
+      return Err(MmapVecDequeError::Corruption { chunk: None, details: "metadata has no format header".to_string() });
+    }
+    let mut meta = Metadata {
+      type_name: legacy.type_name,
+      layout_version: 0,
+      element_size: legacy.element_size,
+      chunk_size: legacy.chunk_size,
+      start: legacy.start,
+      end: legacy.end,
+      checksums: Vec::new(),
+      capacity: None,
+      overflow: Overflow::Evict,
+      generation: 0,
+      // Chunk files of version 0 hold native-endian elements, and FORMAT.md requires such deques
+      // to be upgraded on the platform that wrote them.
+      little_endian: LITTLE_ENDIAN,
+      pointer_width: POINTER_WIDTH,
+      element_align: 0,
+      portable: false,
+    };
+    // There is nothing to verify the chunks against, so their current contents are taken as
+    // committed.
+    let chunk_size = meta.chunk_size as u64;
+    let (first, last) = meta.chunk_range();
+    for chunk in first..=last {
+      let (from, to) = live_bounds(meta.start, meta.end, chunk, chunk_size);
+      let mut bytes = vec![0u8; (to - from) as usize * meta.element_size];
+      if !bytes.is_empty() {
+        let mut file = File::open(chunk_file(dir, chunk, None, 0))?;
+        file.seek(SeekFrom::Start((from % chunk_size) * meta.element_size as u64))?;
+        file.read_exact(&mut bytes)?;
+      }
+      meta.checksums.push(crc32c::crc32c(&bytes));
+    }
+    Ok((meta, true))
+  }
+
+  /// Checks that the stored element type matches `T` as identified by `options`. Returns whether
+  /// the stored name was only accepted through `DequeOptions::accept_type_name`.
+  pub(crate) fn check_type<T>(&self, options: &DequeOptions) -> Result<bool, MmapVecDequeError> {
+    let element_size = size_of::<T>();
+    if self.element_size != element_size {
+      return Err(MmapVecDequeError::ElementSizeMismatch {
+        stored: self.element_size,
+        requested: element_size,
+      });
+    }
+    let (type_name, layout_version) = options.type_identity::<T>();
+    let accepted = self.type_name != type_name;
+    if accepted && !options.accepted_type_names.contains(&self.type_name) {
+      return Err(MmapVecDequeError::TypeMismatch {
+        stored: self.type_name.clone(),
+        requested: type_name,
+      });
+    }
+    if self.layout_version != layout_version {
+      return Err(MmapVecDequeError::LayoutVersionMismatch {
+        stored: self.layout_version,
+        requested: layout_version,
+      });
+    }
+    let element_align = align_of::<T>();
+    if self.element_align != 0 && self.element_align != element_align {
+      return Err(MmapVecDequeError::AlignmentMismatch {
+        stored: self.element_align,
+        requested: element_align,
+      });
+    }
+    Ok(accepted)
+  }
+
+  /// Checks that the chunk files can be read on this platform. Returns whether the elements of a
+  /// portable deque are stored in the other byte order, which is only allowed if the caller is
+  /// `convertible`, i.e. can convert them to this platform's byte order.
+  pub(crate) fn check_platform(&self, convertible: bool) -> Result<bool, MmapVecDequeError> {
+    let convert = self.little_endian != LITTLE_ENDIAN;
+    if (!self.portable && self.pointer_width != POINTER_WIDTH) || (convert && !(self.portable && convertible)) {
+      return Err(MmapVecDequeError::PlatformMismatch {
+        stored: describe_platform(self.little_endian, self.pointer_width),
+        current: describe_platform(LITTLE_ENDIAN, POINTER_WIDTH),
+      });
+    }
+    Ok(convert)
+  }
+
+  pub(crate) fn len(&self) -> usize {
+    (self.end - self.start) as usize
+  }
+
+  pub(crate) fn chunk_range(&self) -> (u64, u64) {
+    chunk_range(self.start, self.end, self.chunk_size as u64)
+  }
+
+  /// Number of the file holding `chunk`, which bounded deques reuse every `ring()` chunks.
+  pub(crate) fn file_number(&self, chunk: u64) -> u64 {
+    match self.ring() {
+      Some(ring) => chunk % ring,
+      None => chunk,
+    }
+  }
+
+  /// Number of chunk files a bounded deque cycles through: enough for `capacity` elements at any
+  /// offset within their first chunk.
+  pub(crate) fn ring(&self) -> Option<u64> {
+    self.capacity.map(|capacity| capacity.div_ceil(self.chunk_size as u64) + 1)
+  }
+}
+
+/// Checks that deques of `T`s are supported at all.
+pub(crate) fn check_element<T>() -> Result<(), MmapVecDequeError> {
+  if size_of::<T>() == 0 {
+    return Err(MmapVecDequeError::ZeroSizedType);
+  }
+  if align_of::<T>() > MAX_ALIGN {
+    return Err(MmapVecDequeError::Other(format!("Element alignment above {} bytes is not supported", MAX_ALIGN)));
+  }
+  Ok(())
+}
+
+fn describe_platform(little_endian: bool, pointer_width: u32) -> String {
+  format!("{}-bit {}-endian", pointer_width, if little_endian { "little" } else { "big" })
+}
+
+/// First and last chunk index that hold elements of `[start, end)` (just the start chunk when empty).
+pub(crate) fn chunk_range(start: u64, end: u64, chunk_size: u64) -> (u64, u64) {
+  let start_chunk = start / chunk_size;
+  let end_chunk = if end == start {
+    start_chunk
+  } else {
+    (end - 1) / chunk_size
+  };
+  (start_chunk, end_chunk)
+}
+
+/// Range of global indices in `chunk` that lie inside `[start, end)`.
+pub(crate) fn live_bounds(start: u64, end: u64, chunk: u64, chunk_size: u64) -> (u64, u64) {
+  let from = start.max(chunk * chunk_size);
+  let to = end.min((chunk + 1) * chunk_size);
+  (from, to.max(from))
+}
+
+/// Path of the file holding chunk `index`. Bounded deques reuse `ring` files circularly, and each
+/// `generation` after the first has its own file names.
+pub(crate) fn chunk_file(dir: &Path, index: u64, ring: Option<u64>, generation: u64) -> PathBuf {
+  let file = match ring {
+    Some(ring) => index % ring,
+    None => index,
+  };
+  match generation {
+    0 => dir.join(format!("chunk_{}.bin", file)),
+    generation => dir.join(format!("chunk_{}.gen{}.bin", file, generation)),
+  }
+}
+
+/// The file index and generation of a chunk file name as produced by `chunk_file`, or `None` for
+/// other files.
+pub(crate) fn chunk_file_index(name: &OsStr) -> Option<(u64, u64)> {
+  let name = name.to_str()?.strip_prefix("chunk_")?.strip_suffix(".bin")?;
+  match name.split_once(".gen") {
+    Some((index, generation)) => Some((index.parse().ok()?, generation.parse().ok()?)),
+    None => Some((name.parse().ok()?, 0)),
+  }
+}
@@ -1,32 +1,29 @@
 use crate::error::MmapVecDequeError;
+use crate::journal::Journal;
+use crate::lock::DirLock;
+use crate::metadata::{check_element, chunk_file, chunk_file_index, chunk_range, live_bounds, Metadata, LITTLE_ENDIAN, POINTER_WIDTH};
+use crate::notify::CommitCounter;
+use crate::options::{DequeOptions, Overflow};
+use crate::persistent::PersistentType;
+use crate::portable::SwapBytes;
+use crate::reader::{Reader, Shared};
 use parking_lot::Mutex;
-use serde::{Serialize, Deserialize};
+use std::collections::hash_map::Entry;
+use std::collections::{btree_map, BTreeMap, HashMap, HashSet};
 use std::fs::{self, OpenOptions, File};
-use std::io::Write;
+use std::io::{self, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::{ptr, mem::size_of};
+use std::{ptr, mem::{align_of, size_of}};
 use memmap2::{MmapMut, MmapOptions};
+use std::iter::FusedIterator;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds, RangeInclusive};
+use std::sync::Arc;
 use atomicwrites::{AtomicFile, AllowOverwrite};
 
 const DEFAULT_CHUNK_SIZE: usize = 10_000;
 const LARGE_OFFSET: u64 = 1 << 32;
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Metadata {
-  type_name: String,
-  element_size: usize,
-  chunk_size: usize,
-  start: u64,
-  end: u64,
-}
-
-impl Metadata {
-  fn len(&self) -> usize {
-    (self.end - self.start) as usize
-  }
-}
-
 struct Chunk {
   mmap: MmapMut,
   file: File,
@@ -34,80 +31,392 @@ struct Chunk {
 
 pub struct MmapVecDeque<T: Copy> {
   dir: PathBuf,
+  counter: CommitCounter, // Cross-process commit counter, bumped after every commit
   meta: Mutex<Metadata>,
-  chunks: Mutex<Vec<Chunk>>,
-  base_chunk: Mutex<u64>, // Tracks which chunk index corresponds to chunks[0]
+  chunks: Mutex<BTreeMap<u64, Chunk>>, // Mapped chunk files, by file number (see `Metadata::file_number`)
   _marker: PhantomData<T>,
   dirty: Mutex<bool>,
+  committed: Mutex<(u64, u64)>, // [start, end) as of the last commit
+  journal: Mutex<Option<Journal>>, // Open while committed slots have been overwritten since the last commit
+  journaled: Mutex<HashSet<u64>>, // Committed slots whose original bytes are already in the journal
+  written_chunks: Mutex<HashSet<u64>>, // Files of chunks written since the last commit, whose checksums must be recomputed
+  shared: Arc<Shared<T>>, // Committed state published to readers
+  protected: Mutex<(u64, u64)>, // Slots readers may still read; they are saved in `shared.originals` before overwriting
+  quota: Option<u64>, // Maximum bytes on disk, checked before creating chunk files
+  swap_bytes: Option<SwapBytes>, // Converts the elements of a portable deque on big-endian machines
 }
 
 impl<T: Copy> MmapVecDeque<T> {
-  pub fn open_or_create(dir: &Path, chunk_size: Option<usize>) -> Result<Self, MmapVecDequeError> {
-    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+  /// Opens the deque stored in `dir`, creating the directory and an empty deque if needed.
+  ///
+  /// The directory stays exclusively locked while the deque is open, so opening it again from
+  /// this or any other process fails with `MmapVecDequeError::Locked`. With the `bytemuck`
+  /// feature, `open_pod` does the same without `unsafe` for types it can check.
+  ///
+  /// # Safety
+  ///
+  /// Elements are read straight from the chunk files, which may hold zeros, bytes written for
+  /// another type, or corrupted data. Every bit pattern of `size_of::<T>()` bytes must therefore be
+  /// a valid `T`, which rules out `bool`, `char`, most enums, references and types containing
+  /// them.
+  pub unsafe fn open_or_create(dir: &Path, chunk_size: Option<usize>) -> Result<Self, MmapVecDequeError> {
+    Self::open_with(dir, &Self::chunk_size_options(chunk_size))
+  }
+
+  /// Same as `open_or_create`, restricted to types for which any bytes are a valid value:
+  ///
+  /// ```compile_fail
+  /// # use mmap_vecdeque::MmapVecDeque;
+  /// let deque = MmapVecDeque::<bool>::open_pod("flags".as_ref(), None);
+  /// ```
+  #[cfg(feature = "bytemuck")]
+  pub fn open_pod(dir: &Path, chunk_size: Option<usize>) -> Result<Self, MmapVecDequeError>
+  where
+    T: bytemuck::Pod,
+  {
+    // Safety: every bit pattern is a valid `Pod` value.
+    unsafe { Self::open_or_create(dir, chunk_size) }
+  }
+
+  /// Same as `open_or_create`, identifying elements by `T::TYPE_NAME` and `T::LAYOUT_VERSION`
+  /// instead of `std::any::type_name`. Use `DequeOptions::persistent` to combine this with other
+  /// settings, such as accepting the name an existing deque was stored under.
+  ///
+  /// # Safety
+  ///
+  /// See `open_or_create`.
+  pub unsafe fn open_persistent(dir: &Path, chunk_size: Option<usize>) -> Result<Self, MmapVecDequeError>
+  where
+    T: PersistentType,
+  {
+    Self::open_with(dir, Self::chunk_size_options(chunk_size).persistent::<T>())
+  }
+
+  fn chunk_size_options(chunk_size: Option<usize>) -> DequeOptions {
+    let mut options = DequeOptions::new();
+    if let Some(chunk_size) = chunk_size {
+      options.chunk_size(chunk_size);
+    }
+    options
+  }
+
+  pub(crate) fn open_with(dir: &Path, options: &DequeOptions) -> Result<Self, MmapVecDequeError> {
+    let chunk_size = options.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
     let element_size = size_of::<T>();
-    if element_size == 0 {
-      return Err(MmapVecDequeError::ZeroSizedType);
+    check_element::<T>()?;
+    let swap_bytes = options.portable_swap::<T>()?;
+    if options.capacity.is_some_and(|(capacity, _)| capacity == 0) {
+      return Err(MmapVecDequeError::Other("Capacity of a bounded deque must be at least 1".to_string()));
     }
 
     if !dir.exists() {
       fs::create_dir_all(dir)?;
     }
+    let lock = DirLock::exclusive(dir)?;
+    let counter = CommitCounter::create(dir)?;
 
-    let metadata_file = dir.join("metadata.bin");
-    let type_name = std::any::type_name::<T>().to_string();
-
-    let meta = if metadata_file.exists() {
-      let data = fs::read(&metadata_file)?;
-      let meta: Metadata = postcard::from_bytes(&data)?;
-      if meta.element_size != element_size {
-        return Err(MmapVecDequeError::ElementSizeMismatch {
-          stored: meta.element_size,
-          requested: element_size,
-        });
-      }
-      if meta.type_name != type_name {
-        return Err(MmapVecDequeError::TypeMismatch {
-          stored: meta.type_name.clone(),
-          requested: type_name.clone(),
-        });
-      }
+    let meta = if dir.join("metadata.bin").exists() {
+      let (meta, upgraded) = Metadata::read_upgrading(dir)?;
+      let renamed = meta.check_type::<T>(options)?;
+      meta.check_platform(swap_bytes.is_some())?;
       if meta.chunk_size != chunk_size {
         return Err(MmapVecDequeError::ChunkSizeMismatch {
           stored: meta.chunk_size,
           requested: chunk_size,
         });
       }
+      let requested = options.capacity.map(|(capacity, _)| capacity);
+      if requested.is_some() && requested != meta.capacity {
+        return Err(MmapVecDequeError::CapacityMismatch {
+          stored: meta.capacity,
+          requested,
+        });
+      }
+      let mut meta = Self::recover_journal(dir, meta)?;
+      Self::remove_stale_chunk_files(dir, meta.chunk_range(), meta.ring(), meta.generation)?;
+      if let (false, Some(swap_bytes)) = (meta.little_endian, swap_bytes) {
+        meta = Self::convert_byte_order(dir, meta, swap_bytes)?;
+      }
+      if let Some((_, overflow)) = options.capacity {
+        meta.overflow = overflow;
+      }
+      if renamed {
+        meta.type_name = options.type_identity::<T>().0;
+      }
+      let mut changed = renamed || upgraded;
+      if meta.element_align == 0 {
+        meta.element_align = align_of::<T>();
+        changed = true;
+      }
+      if swap_bytes.is_some() && !meta.portable {
+        meta.portable = true;
+        changed = true;
+      }
+      if changed {
+        Self::atomic_write_metadata(dir, &meta)?;
+      }
       meta
     } else {
+      let (type_name, layout_version) = options.type_identity::<T>();
       let meta = Metadata {
-        type_name: type_name.clone(),
+        type_name,
+        layout_version,
         element_size,
         chunk_size,
         start: LARGE_OFFSET,
         end: LARGE_OFFSET,
+        checksums: vec![crc32c::crc32c(&[])],
+        capacity: options.capacity.map(|(capacity, _)| capacity),
+        overflow: options.capacity.map_or(Overflow::Evict, |(_, overflow)| overflow),
+        generation: 0,
+        little_endian: LITTLE_ENDIAN || swap_bytes.is_some(),
+        pointer_width: POINTER_WIDTH,
+        element_align: align_of::<T>(),
+        portable: swap_bytes.is_some(),
       };
       Self::atomic_write_metadata(dir, &meta)?;
       meta
     };
 
+    let committed = (meta.start, meta.end);
+    // Portable deques are stored little-endian, so big-endian machines convert every element.
+    let swap_bytes = swap_bytes.filter(|_| meta.little_endian != LITTLE_ENDIAN);
+    let shared = Arc::new(Shared::opened(dir, &meta, Some(lock), HashMap::new(), swap_bytes));
     let deque = MmapVecDeque {
       dir: dir.to_path_buf(),
+      counter,
       meta: Mutex::new(meta),
-      chunks: Mutex::new(Vec::new()),
-      base_chunk: Mutex::new(0),
+      chunks: Mutex::new(BTreeMap::new()),
       _marker: PhantomData,
       dirty: Mutex::new(false),
+      committed: Mutex::new(committed),
+      journal: Mutex::new(None),
+      journaled: Mutex::new(HashSet::new()),
+      written_chunks: Mutex::new(HashSet::new()),
+      shared,
+      protected: Mutex::new(committed),
+      quota: options.quota,
+      swap_bytes,
     };
 
     deque.load_chunks()?;
+    deque.verify_chunks(options.verify_on_open)?;
     Ok(deque)
   }
 
+  /// Converts the deque in `dir` from elements of type `Old` to elements of type `T`, e.g. after a
+  /// field was added to the element struct.
+  ///
+  /// Uncommitted changes left by a crashed writer are rolled back, then the converted elements are
+  /// written to a new set of chunk files. Switching to them is a single atomic metadata write, so
+  /// a crash leaves either the old deque or the migrated one; leftover files are deleted on the
+  /// next open. The chunk size and bounded capacity are kept. `Old` is checked against the stored
+  /// type like `open_or_create` does; use `DequeOptions::migrate` for other type identities.
+  ///
+  /// # Safety
+  ///
+  /// Both `Old` and `T` must meet the requirements of `open_or_create`.
+  pub unsafe fn migrate<Old: Copy>(dir: &Path, f: impl FnMut(Old) -> T) -> Result<(), MmapVecDequeError> {
+    Self::migrate_with(dir, &DequeOptions::new(), f)
+  }
+
+  pub(crate) fn migrate_with<Old: Copy>(dir: &Path, options: &DequeOptions, mut f: impl FnMut(Old) -> T) -> Result<(), MmapVecDequeError> {
+    let element_size = size_of::<T>();
+    check_element::<T>()?;
+    let swap_bytes = options.portable_swap::<T>()?;
+    let stored = Metadata::read(dir)?;
+    let mut old_options = DequeOptions::new();
+    old_options.chunk_size(stored.chunk_size);
+    if let Some(capacity) = stored.capacity {
+      old_options.bounded(capacity as usize, stored.overflow);
+    }
+    // The layout version of the old elements is whatever was stored; only their name is checked.
+    old_options.type_name = Some((std::any::type_name::<Old>().to_string(), stored.layout_version));
+    old_options.accepted_type_names = options.accepted_type_names.clone();
+    let old = MmapVecDeque::<Old>::open_with(dir, &old_options)?;
+
+    let old_meta = old.meta.lock();
+    let (type_name, layout_version) = options.type_identity::<T>();
+    let mut meta = Metadata {
+      type_name,
+      layout_version,
+      element_size,
+      chunk_size: options.chunk_size.unwrap_or(old_meta.chunk_size),
+      start: LARGE_OFFSET,
+      end: LARGE_OFFSET + old_meta.len() as u64,
+      checksums: Vec::new(),
+      capacity: old_meta.capacity,
+      overflow: old_meta.overflow,
+      generation: old_meta.generation + 1,
+      little_endian: LITTLE_ENDIAN || swap_bytes.is_some(),
+      pointer_width: POINTER_WIDTH,
+      element_align: align_of::<T>(),
+      portable: swap_bytes.is_some(),
+    };
+    drop(old_meta);
+
+    let chunk_size = meta.chunk_size as u64;
+    let (first, last) = meta.chunk_range();
+    let swap_bytes = swap_bytes.filter(|_| !LITTLE_ENDIAN);
+    let mut values = old.iter().map(&mut f).map(|value| match swap_bytes {
+      Some(swap_bytes) => swap_bytes.swap_value(value),
+      None => value,
+    });
+    for chunk in first..=last {
+      let (from, to) = live_bounds(meta.start, meta.end, chunk, chunk_size);
+      let offset = (from % chunk_size) as usize * element_size;
+      let mut bytes = vec![0u8; meta.chunk_size * element_size];
+      for (i, value) in values.by_ref().take((to - from) as usize).enumerate() {
+        unsafe { ptr::write_unaligned(bytes.as_mut_ptr().add(offset + i * element_size) as *mut T, value) };
+      }
+      meta.checksums.push(crc32c::crc32c(&bytes[offset..offset + (to - from) as usize * element_size]));
+      Self::write_chunk_file(&chunk_file(dir, chunk, meta.ring(), meta.generation), &bytes)?;
+    }
+    drop(values);
+    let dir_file = OpenOptions::new().read(true).open(dir)?;
+    dir_file.sync_all()?;
+
+    // The commit point: from here on the deque holds `T`s in the new generation of chunk files.
+    Self::atomic_write_metadata(dir, &meta)?;
+    // The old deque's shared state holds the directory lock until the files are cleaned up.
+    let MmapVecDeque { shared: lock, counter, chunks, .. } = old;
+    drop(chunks);
+    Self::remove_stale_chunk_files(dir, meta.chunk_range(), meta.ring(), meta.generation)?;
+    // Wake up followers, whose next refresh reports the migration.
+    counter.bump();
+    drop(lock);
+    Ok(())
+  }
+
+  /// Converts the elements of a deque stored big-endian to little-endian, the byte order of
+  /// portable deques. Like `migrate_with`, the converted elements go to a new generation of chunk
+  /// files, which the metadata written last switches to.
+  fn convert_byte_order(dir: &Path, mut meta: Metadata, swap_bytes: SwapBytes) -> Result<Metadata, MmapVecDequeError> {
+    let chunk_size = meta.chunk_size as u64;
+    let element_size = meta.element_size;
+    let generation = meta.generation + 1;
+    let (first, last) = meta.chunk_range();
+    let mut checksums = Vec::new();
+    for (chunk, &expected) in (first..=last).zip(&meta.checksums) {
+      let (from, to) = live_bounds(meta.start, meta.end, chunk, chunk_size);
+      let mut bytes = match fs::read(chunk_file(dir, chunk, meta.ring(), meta.generation)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound && from == to => vec![0u8; meta.chunk_size * element_size],
+        result => result?,
+      };
+      let offset = (from % chunk_size) as usize * element_size;
+      let Some(live) = bytes.get_mut(offset..offset + (to - from) as usize * element_size) else {
+        return Err(MmapVecDequeError::Corruption { chunk: Some(chunk), details: "chunk file is truncated".to_string() });
+      };
+      // Checked first, so that the new checksums don't vouch for corrupted elements.
+      let actual = crc32c::crc32c(live);
+      if actual != expected {
+        return Err(MmapVecDequeError::Corruption {
+          chunk: Some(chunk),
+          details: format!("checksum mismatch: expected {:08x}, found {:08x}", expected, actual),
+        });
+      }
+      for element in live.chunks_exact_mut(element_size) {
+        swap_bytes.swap(element);
+      }
+      checksums.push(crc32c::crc32c(live));
+      Self::write_chunk_file(&chunk_file(dir, chunk, meta.ring(), generation), &bytes)?;
+    }
+    let dir_file = OpenOptions::new().read(true).open(dir)?;
+    dir_file.sync_all()?;
+
+    meta.checksums = checksums;
+    meta.generation = generation;
+    meta.little_endian = true;
+    Self::atomic_write_metadata(dir, &meta)?;
+    Self::remove_stale_chunk_files(dir, meta.chunk_range(), meta.ring(), meta.generation)?;
+    Ok(meta)
+  }
+
+  fn write_chunk_file(path: &Path, bytes: &[u8]) -> Result<(), MmapVecDequeError> {
+    let mut file = OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    Ok(())
+  }
+
+  /// Opens an existing deque for reading only, without creating or modifying any file.
+  ///
+  /// The directory is locked shared, so any number of readers can coexist but no writer can
+  /// open it meanwhile. Fails if `dir` or its metadata doesn't exist.
+  ///
+  /// # Safety
+  ///
+  /// See `open_or_create`.
+  pub unsafe fn open_read_only(dir: &Path) -> Result<Reader<T>, MmapVecDequeError> {
+    Reader::open(dir, false, &DequeOptions::new())
+  }
+
+  /// Same as `open_read_only`, restricted to types for which any bytes are a valid value.
+  #[cfg(feature = "bytemuck")]
+  pub fn open_read_only_pod(dir: &Path) -> Result<Reader<T>, MmapVecDequeError>
+  where
+    T: bytemuck::Pod,
+  {
+    // Safety: every bit pattern is a valid `Pod` value.
+    unsafe { Self::open_read_only(dir) }
+  }
+
+  /// Opens an existing deque for reading alongside a writer, typically in another process.
+  ///
+  /// The directory isn't locked, so a writer can open it before or after. `refresh()` reloads the
+  /// latest commit from disk, and `Reader::wait_for_commit` wakes up as soon as the writer's
+  /// `commit()` completes. Elements the writer overwrites in place (e.g. with `iter_mut`) may
+  /// become visible before they are committed, and checksums aren't verified.
+  ///
+  /// # Safety
+  ///
+  /// See `open_or_create`.
+  pub unsafe fn open_follower(dir: &Path) -> Result<Reader<T>, MmapVecDequeError> {
+    Reader::open(dir, true, &DequeOptions::new())
+  }
+
+  /// Same as `open_follower`, restricted to types for which any bytes are a valid value.
+  #[cfg(feature = "bytemuck")]
+  pub fn open_follower_pod(dir: &Path) -> Result<Reader<T>, MmapVecDequeError>
+  where
+    T: bytemuck::Pod,
+  {
+    // Safety: every bit pattern is a valid `Pod` value.
+    unsafe { Self::open_follower(dir) }
+  }
+
+  /// Returns a reader showing the last commit, which can be sent to other threads while this
+  /// deque keeps changing. Unlike opening a reader from a directory, this is safe: the
+  /// requirements on `T` were already met to open this deque. The directory stays locked until
+  /// both the deque and all of its readers are dropped, so no other writer can change the
+  /// elements a reader still shows.
+  pub fn reader(&self) -> Result<Reader<T>, MmapVecDequeError> {
+    if !self.shared.has_readers() {
+      // Originals are only kept while readers exist; catch up on slots overwritten so far.
+      let element_size = self.meta.lock().element_size;
+      if let Some(contents) = Journal::read(&self.dir, element_size)? {
+        let mut originals = self.shared.originals.write();
+        for (index, bytes) in contents.records {
+          originals.latest().entry(index).or_insert_with(|| self.swapped(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) }));
+        }
+      }
+    }
+    Reader::new(self.shared.clone())
+  }
+
+  pub(crate) fn shared(&self) -> &Arc<Shared<T>> {
+    &self.shared
+  }
+
   fn atomic_write_metadata(dir: &Path, meta: &Metadata) -> Result<(), MmapVecDequeError> {
-    let data = postcard::to_stdvec(meta)?;
+    let data = meta.encode()?;
+    Self::atomic_write_metadata_bytes(dir, &data)
+  }
+
+  fn atomic_write_metadata_bytes(dir: &Path, data: &[u8]) -> Result<(), MmapVecDequeError> {
     let af = AtomicFile::new(dir.join("metadata.bin"), AllowOverwrite);
     af.write(|f| {
-      f.write_all(&data)
+      f.write_all(data)
     })?;
     let dir_file = OpenOptions::new().read(true).open(dir)?;
     dir_file.sync_all()?;
@@ -133,16 +442,78 @@ impl<T: Copy> MmapVecDeque<T> {
     chunks.clear();
     for ch in start_chunk..(start_chunk + chunk_count) {
       let (mmap, file) = self.open_chunk(ch, true)?;
-      chunks.push(Chunk { mmap, file });
+      chunks.insert(self.file_number(ch), Chunk { mmap, file });
     }
-    drop(chunks);
+    Ok(())
+  }
 
-    *self.base_chunk.lock() = start_chunk;
+  /// Replays a journal left behind by a commit that never completed, restoring the
+  /// previously committed metadata and every committed slot overwritten after it.
+  fn recover_journal(dir: &Path, meta: Metadata) -> Result<Metadata, MmapVecDequeError> {
+    let Some(contents) = Journal::read(dir, meta.element_size)? else {
+      return Ok(meta);
+    };
+    if contents.metadata.is_empty() {
+      // The journal header never became durable, so no committed slot was overwritten.
+      Journal::remove(dir)?;
+      return Ok(meta);
+    }
+
+    let chunk_size = meta.chunk_size as u64;
+    let ring = meta.ring();
+    let mut files: Vec<(u64, File)> = Vec::new();
+    for (index, bytes) in contents.records {
+      let chunk = index / chunk_size;
+      let pos = match files.iter().position(|(ch, _)| *ch == chunk) {
+        Some(pos) => pos,
+        None => {
+          let file = OpenOptions::new().write(true).open(chunk_file(dir, chunk, ring, meta.generation))?;
+          files.push((chunk, file));
+          files.len() - 1
+        }
+      };
+      let file = &mut files[pos].1;
+      file.seek(SeekFrom::Start((index % chunk_size) * meta.element_size as u64))?;
+      file.write_all(&bytes)?;
+    }
+    for (_, file) in files {
+      file.sync_all()?;
+    }
+
+    Self::atomic_write_metadata_bytes(dir, &contents.metadata)?;
+    Journal::remove(dir)?;
+    Metadata::decode(&contents.metadata)
+  }
+
+  /// Deletes every chunk file outside `[start_chunk, end_chunk]`, e.g. ones left behind by a
+  /// process that died between a commit and the deletion of the chunks it released, and every
+  /// chunk file of another generation. Bounded deques keep all the files of their ring.
+  fn remove_stale_chunk_files(dir: &Path, (start_chunk, end_chunk): (u64, u64), ring: Option<u64>, generation: u64) -> Result<(), MmapVecDequeError> {
+    let mut removed = false;
+    for entry in fs::read_dir(dir)? {
+      let entry = entry?;
+      if let Some((index, file_generation)) = chunk_file_index(&entry.file_name()) {
+        let stale = file_generation != generation
+          || match ring {
+            Some(ring) => index >= ring,
+            None => index < start_chunk || index > end_chunk,
+          };
+        if stale {
+          fs::remove_file(entry.path())?;
+          removed = true;
+        }
+      }
+    }
+    if removed {
+      let dir_file = OpenOptions::new().read(true).open(dir)?;
+      dir_file.sync_all()?;
+    }
     Ok(())
   }
 
   fn chunk_path(&self, index: u64) -> PathBuf {
-    self.dir.join(format!("chunk_{}.bin", index))
+    let meta = self.meta.lock();
+    chunk_file(&self.dir, index, meta.ring(), meta.generation)
   }
 
   fn open_chunk(&self, index: u64, create: bool) -> Result<(MmapMut, File), MmapVecDequeError> {
@@ -152,11 +523,18 @@ impl<T: Copy> MmapVecDeque<T> {
 
     let path = self.chunk_path(index);
     if create && !path.exists() {
-      let f = OpenOptions::new().write(true).create(true).open(&path)?;
+      let f = OpenOptions::new().write(true).create(true).truncate(false).open(&path)?;
       f.set_len(chunk_byte_size as u64)?;
       f.sync_all()?;
     }
     let file = OpenOptions::new().read(true).write(true).open(&path)?;
+    let file_len = file.metadata()?.len();
+    if file_len < chunk_byte_size as u64 {
+      return Err(MmapVecDequeError::Corruption {
+        chunk: Some(index),
+        details: format!("chunk file is {} bytes, expected {}", file_len, chunk_byte_size),
+      });
+    }
     let mmap = unsafe {
       MmapOptions::new()
         .len(chunk_byte_size)
@@ -165,85 +543,428 @@ impl<T: Copy> MmapVecDeque<T> {
     Ok((mmap, file))
   }
 
+  /// Fails with `QuotaExceeded` if creating the files of `chunks` that don't exist yet would take
+  /// disk usage past the quota.
+  fn check_quota(&self, chunks: RangeInclusive<u64>) -> Result<(), MmapVecDequeError> {
+    let Some(quota) = self.quota else {
+      return Ok(());
+    };
+    let new_files = chunks.filter(|&chunk| !self.chunk_path(chunk).exists()).count() as u64;
+    if new_files == 0 {
+      return Ok(());
+    }
+    let meta = self.meta.lock();
+    let chunk_byte_size = (meta.chunk_size * meta.element_size) as u64;
+    drop(meta);
+
+    let needed = self.disk_usage()? + new_files * chunk_byte_size;
+    if needed > quota {
+      return Err(MmapVecDequeError::QuotaExceeded { quota, needed });
+    }
+    Ok(())
+  }
+
   fn flush_all_chunks(&self) -> Result<(), MmapVecDequeError> {
     let chunks = self.chunks.lock();
-    for chunk in chunks.iter() {
+    for chunk in chunks.values() {
       chunk.mmap.flush()?;
       chunk.file.sync_all()?;
     }
     Ok(())
   }
 
-  fn global_to_local(&self, index: u64) -> (usize, usize) {
+  /// The file number of the chunk holding `index`, which keys its mapping in `chunks`, and the
+  /// position of `index` within the chunk.
+  fn global_to_local(&self, index: u64) -> (u64, usize) {
     let meta = self.meta.lock();
     let chunk_size = meta.chunk_size as u64;
+    let chunk_idx = meta.file_number(index / chunk_size);
     drop(meta);
-
-    let base = *self.base_chunk.lock();
-    let chunk_idx = ((index / chunk_size) - base) as usize;
     let elem_idx = (index % chunk_size) as usize;
     (chunk_idx, elem_idx)
   }
 
+  fn file_number(&self, chunk: u64) -> u64 {
+    self.meta.lock().file_number(chunk)
+  }
+
+  /// Maps the chunk holding `index`, along with every chunk between it and the live range, creating
+  /// their files if needed. Chunks of a bounded deque that share a file share its mapping.
   fn ensure_capacity_for(&self, index: u64) -> Result<(), MmapVecDequeError> {
     let meta = self.meta.lock();
     let chunk_size = meta.chunk_size as u64;
     let needed_chunk = index / chunk_size;
+    let (first, last) = meta.chunk_range();
     drop(meta);
+    let needed = if needed_chunk < first {
+      needed_chunk..=first - 1
+    } else if needed_chunk > last {
+      last + 1..=needed_chunk
+    } else {
+      needed_chunk..=needed_chunk
+    };
 
     let mut chunks = self.chunks.lock();
-    let base = *self.base_chunk.lock();
-    let current_count = chunks.len() as u64;
-    if current_count == 0 {
-      let (mmap, file) = self.open_chunk(needed_chunk, true)?;
-      chunks.push(Chunk { mmap, file });
-      drop(chunks);
-      *self.base_chunk.lock() = needed_chunk;
-      return Ok(());
+    let missing: Vec<u64> = needed.filter(|&chunk| !chunks.contains_key(&self.file_number(chunk))).collect();
+    if let (Some(&lo), Some(&hi)) = (missing.iter().min(), missing.iter().max()) {
+      self.check_quota(lo..=hi)?;
     }
+    for chunk in missing {
+      if let btree_map::Entry::Vacant(entry) = chunks.entry(self.file_number(chunk)) {
+        let (mmap, file) = self.open_chunk(chunk, true)?;
+        entry.insert(Chunk { mmap, file });
+      }
+    }
+    Ok(())
+  }
 
-    let current_start_chunk = base;
-    let current_end_chunk = current_start_chunk + current_count - 1;
+  /// Durably journals the committed contents of every slot in `[from, to)` that is about to be
+  /// overwritten, so a crash or `rollback()` before the next commit can restore them. Slots that
+  /// readers may still read are also saved for them.
+  ///
+  /// In a bounded deque a slot also holds the elements at the same position of every other lap
+  /// around the ring, so the committed or readable ones among those are saved instead.
+  fn journal_range(&self, from: u64, to: u64) -> Result<(), MmapVecDequeError> {
+    let (committed_start, committed_end) = *self.committed.lock();
+    let (protected_start, protected_end) = *self.protected.lock();
+    let (lo, hi) = (committed_start.min(protected_start), committed_end.max(protected_end));
+    let meta = self.meta.lock();
+    let element_size = meta.element_size;
+    let lap = meta.ring().map(|ring| ring * meta.chunk_size as u64);
+    drop(meta);
+    let (from, to) = match lap {
+      Some(_) => (from, to),
+      None => (from.max(lo), to.min(hi)),
+    };
+    if from >= to || lo >= hi {
+      return Ok(());
+    }
 
-    if needed_chunk > current_end_chunk {
-      // Add chunks at the end
-      for new_idx in (current_end_chunk + 1)..=needed_chunk {
-        let (mmap, file) = self.open_chunk(new_idx, true)?;
-        chunks.push(Chunk { mmap, file });
+    let mut originals = if self.shared.has_readers() { Some(self.shared.originals.write()) } else { None };
+    let mut journaled = self.journaled.lock();
+    let mut journal = self.journal.lock();
+    let mut appended = false;
+    for index in from..to {
+      for alias in slot_aliases(index, lo, hi, lap) {
+        if let Some(originals) = originals.as_mut() {
+          if let Entry::Vacant(entry) = originals.latest().entry(alias) {
+            if let Ok(original) = self.read_element(index) {
+              entry.insert(original);
+            }
+          }
+        }
+        if alias < committed_start || alias >= committed_end || journaled.contains(&alias) {
+          continue;
+        }
+        if journal.is_none() {
+          let saved = fs::read(self.dir.join("metadata.bin"))?;
+          *journal = Some(Journal::create(&self.dir, &saved)?);
+        }
+        let (chunk_idx, elem_idx) = self.global_to_local(index);
+        let chunks = self.chunks.lock();
+        let Some(chunk) = chunks.get(&chunk_idx) else {
+          return Err(MmapVecDequeError::IndexOutOfRange);
+        };
+        let offset = elem_idx * element_size;
+        let bytes = &chunk.mmap[offset..offset + element_size];
+        journal.as_mut().unwrap().append(alias, bytes)?;
+        drop(chunks);
+        journaled.insert(alias);
+        appended = true;
       }
-    } else if needed_chunk < current_start_chunk {
-      // Add chunks at the front
-      for new_idx in (needed_chunk..current_start_chunk).rev() {
-        let (mmap, file) = self.open_chunk(new_idx, true)?;
-        chunks.insert(0, Chunk { mmap, file });
-      }
-      drop(chunks);
-      *self.base_chunk.lock() = needed_chunk;
-      return Ok(());
     }
-    drop(chunks);
+    if appended {
+      journal.as_mut().unwrap().sync()?;
+    }
     Ok(())
   }
 
+  /// Capacity of a bounded deque that evicts when full.
+  fn evicting_capacity(&self) -> Option<usize> {
+    let meta = self.meta.lock();
+    match meta.overflow {
+      Overflow::Evict => meta.capacity.map(|capacity| capacity as usize),
+      Overflow::Fail => None,
+    }
+  }
+
+  /// In a bounded deque, makes room for `extra` more elements by evicting from the front (when
+  /// adding at the back) or from the back, or fails with `Full`, depending on the overflow policy.
+  fn make_room(&self, extra: u64, at_back: bool) -> Result<(), MmapVecDequeError> {
+    let mut meta = self.meta.lock();
+    let Some(capacity) = meta.capacity else {
+      return Ok(());
+    };
+    let len = meta.len() as u64;
+    let excess = (len + extra).saturating_sub(capacity);
+    if excess == 0 {
+      return Ok(());
+    }
+    match meta.overflow {
+      Overflow::Fail => Err(MmapVecDequeError::Full { capacity }),
+      Overflow::Evict => {
+        let evicted = excess.min(len);
+        if at_back {
+          meta.start += evicted;
+        } else {
+          meta.end -= evicted;
+        }
+        Ok(())
+      }
+    }
+  }
+
   fn write_element(&self, index: u64, value: T) -> Result<(), MmapVecDequeError> {
     self.ensure_capacity_for(index)?;
+    self.journal_range(index, index + 1)?;
+    self.store_element(index, value)
+  }
+
+  /// Records that the elements in `[from, to)` changed, so the next commit flushes them and
+  /// recomputes the checksums of their chunks.
+  fn mark_written(&self, from: u64, to: u64) {
+    *self.dirty.lock() = true;
+    if from >= to {
+      return;
+    }
+    let meta = self.meta.lock();
+    let chunk_size = meta.chunk_size as u64;
+    let mut written = self.written_chunks.lock();
+    for chunk in (from / chunk_size)..=((to - 1) / chunk_size) {
+      written.insert(meta.file_number(chunk));
+    }
+  }
+
+  /// CRC32C of the elements of `chunk` whose global indices lie in `[from, to)`.
+  fn chunk_checksum(&self, chunk: u64, from: u64, to: u64) -> Result<u32, MmapVecDequeError> {
+    if from >= to {
+      return Ok(crc32c::crc32c(&[]));
+    }
+    let meta = self.meta.lock();
+    let chunk_size = meta.chunk_size as u64;
+    let element_size = meta.element_size;
+    drop(meta);
+
+    let chunks = self.chunks.lock();
+    let Some(mapped) = chunks.get(&self.file_number(chunk)) else {
+      return Err(MmapVecDequeError::IndexOutOfRange);
+    };
+    let offset = (from % chunk_size) as usize * element_size;
+    let len = (to - from) as usize * element_size;
+    Ok(crc32c::crc32c(&mapped.mmap[offset..offset + len]))
+  }
+
+  /// Computes the checksums of the live chunks for `[start, end)`, reusing the committed
+  /// checksum of every chunk whose contents and live bounds are unchanged.
+  fn compute_checksums(&self, start: u64, end: u64) -> Result<Vec<u32>, MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let chunk_size = meta.chunk_size as u64;
+    let old_checksums = meta.checksums.clone();
+    drop(meta);
+    let (committed_start, committed_end) = *self.committed.lock();
+    let old_first = committed_start / chunk_size;
+
+    let written = std::mem::take(&mut *self.written_chunks.lock());
+    let (first, last) = chunk_range(start, end, chunk_size);
+    let mut checksums = Vec::with_capacity((last - first + 1) as usize);
+    for chunk in first..=last {
+      let (from, to) = live_bounds(start, end, chunk, chunk_size);
+      let unchanged = !written.contains(&self.file_number(chunk))
+        && (from, to) == live_bounds(committed_start, committed_end, chunk, chunk_size);
+      let old = chunk.checked_sub(old_first).and_then(|i| old_checksums.get(i as usize));
+      let checksum = match old {
+        Some(old) if unchanged => *old,
+        _ => self.chunk_checksum(chunk, from, to)?,
+      };
+      checksums.push(checksum);
+    }
+    Ok(checksums)
+  }
+
+  /// Verifies the committed elements against the checksums stored at the last commit.
+  ///
+  /// This reads every committed element, so it costs as much as a full scan. Opening a deque only
+  /// verifies its first and last chunk, where an interrupted write would land, unless
+  /// `DequeOptions::verify_on_open` is set; call this to check the rest, or to detect corruption
+  /// that happened while the deque stayed open. Chunks written since the last commit are skipped
+  /// until they are committed.
+  pub fn verify(&self) -> Result<(), MmapVecDequeError> {
+    self.verify_chunks(true)
+  }
+
+  /// Verifies every committed chunk, or only the first and last one.
+  fn verify_chunks(&self, all: bool) -> Result<(), MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let chunk_size = meta.chunk_size as u64;
+    let checksums = meta.checksums.clone();
+    drop(meta);
+    let (committed_start, committed_end) = *self.committed.lock();
+
+    let (first, last) = chunk_range(committed_start, committed_end, chunk_size);
+    if checksums.len() as u64 != last - first + 1 {
+      return Err(MmapVecDequeError::Corruption {
+        chunk: None,
+        details: format!("metadata holds {} checksums for {} chunks", checksums.len(), last - first + 1),
+      });
+    }
+    let written = self.written_chunks.lock().clone();
+    for (chunk, expected) in (first..=last).zip(checksums) {
+      // In a bounded deque, writing a later lap around the ring also overwrites this chunk.
+      if (!all && chunk != first && chunk != last) || written.contains(&self.file_number(chunk)) {
+        continue;
+      }
+      let (from, to) = live_bounds(committed_start, committed_end, chunk, chunk_size);
+      let actual = self.chunk_checksum(chunk, from, to)?;
+      if actual != expected {
+        return Err(MmapVecDequeError::Corruption {
+          chunk: Some(chunk),
+          details: format!("checksum mismatch: expected {:08x}, found {:08x}", expected, actual),
+        });
+      }
+    }
+    Ok(())
+  }
+
+  /// Bytes occupied on disk by `metadata.bin`, the rollback journal and every chunk file in the
+  /// directory, including orphaned chunk files that the next commit or open will delete.
+  pub fn disk_usage(&self) -> Result<u64, MmapVecDequeError> {
+    let mut total = 0;
+    for entry in fs::read_dir(&self.dir)? {
+      let entry = entry?;
+      let name = entry.file_name();
+      if name == "metadata.bin" || entry.path() == Journal::path(&self.dir) || chunk_file_index(&name).is_some() {
+        total += entry.metadata()?.len();
+      }
+    }
+    Ok(total)
+  }
+
+  /// The byte quota enforced on pushes, if any.
+  pub fn quota(&self) -> Option<u64> {
+    self.quota
+  }
+
+  /// Sets or removes the byte quota; see `DequeOptions::quota`. Lowering it below the current
+  /// usage only stops further growth.
+  pub fn set_quota(&mut self, quota: Option<u64>) {
+    self.quota = quota;
+  }
+
+  fn store_bytes(&self, index: u64, bytes: &[u8]) -> Result<(), MmapVecDequeError> {
+    let (chunk_idx, elem_idx) = self.global_to_local(index);
+    let mut chunks = self.chunks.lock();
+    let Some(chunk) = chunks.get_mut(&chunk_idx) else {
+      return Err(MmapVecDequeError::IndexOutOfRange);
+    };
+    let offset = elem_idx * bytes.len();
+    chunk.mmap[offset..offset + bytes.len()].copy_from_slice(bytes);
+    Ok(())
+  }
+
+  fn store_element(&self, index: u64, value: T) -> Result<(), MmapVecDequeError> {
     let (chunk_idx, elem_idx) = self.global_to_local(index);
     let chunks = self.chunks.lock();
     let meta = self.meta.lock();
     let element_size = meta.element_size;
     drop(meta);
 
-    if chunk_idx >= chunks.len() {
+    if !chunks.contains_key(&chunk_idx) {
       return Err(MmapVecDequeError::IndexOutOfRange);
     }
 
-    let mmap = &chunks[chunk_idx].mmap;
+    let mmap = &chunks[&chunk_idx].mmap;
     let ptr = mmap.as_ptr() as *mut u8;
     unsafe {
       let elem_ptr = ptr.add(elem_idx * element_size) as *mut T;
-      ptr::write(elem_ptr, value);
+      ptr::write(elem_ptr, self.swapped(value));
     }
-    *self.dirty.lock() = true;
+    drop(chunks);
+    self.mark_written(index, index + 1);
+    Ok(())
+  }
+
+  /// Copies `values` to the slots starting at `from`, one contiguous run per chunk. The slots
+  /// must be backed by loaded chunks and already journaled.
+  fn store_slice(&self, from: u64, values: &[T]) -> Result<(), MmapVecDequeError> {
+    let chunk_size = self.meta.lock().chunk_size;
+    let chunks = self.chunks.lock();
+    let mut done = 0;
+    while done < values.len() {
+      let (chunk_idx, elem_idx) = self.global_to_local(from + done as u64);
+      if !chunks.contains_key(&chunk_idx) {
+        return Err(MmapVecDequeError::IndexOutOfRange);
+      }
+      let run = (chunk_size - elem_idx).min(values.len() - done);
+      unsafe {
+        let dst = (chunks[&chunk_idx].mmap.as_ptr() as *mut T).add(elem_idx);
+        ptr::copy_nonoverlapping(values.as_ptr().add(done), dst, run);
+        if self.swap_bytes.is_some() {
+          for i in 0..run {
+            ptr::write(dst.add(i), self.swapped(ptr::read(dst.add(i))));
+          }
+        }
+      }
+      done += run;
+    }
+    drop(chunks);
+    self.mark_written(from, from + values.len() as u64);
+    Ok(())
+  }
+
+  /// Copies the elements starting at `from` into `buf`, one contiguous run per chunk.
+  fn load_slice(&self, from: u64, buf: &mut [T]) -> Result<(), MmapVecDequeError> {
+    let chunk_size = self.meta.lock().chunk_size;
+    let chunks = self.chunks.lock();
+    let mut done = 0;
+    while done < buf.len() {
+      let (chunk_idx, elem_idx) = self.global_to_local(from + done as u64);
+      if !chunks.contains_key(&chunk_idx) {
+        return Err(MmapVecDequeError::IndexOutOfRange);
+      }
+      let run = (chunk_size - elem_idx).min(buf.len() - done);
+      unsafe {
+        let src = (chunks[&chunk_idx].mmap.as_ptr() as *const T).add(elem_idx);
+        ptr::copy_nonoverlapping(src, buf.as_mut_ptr().add(done), run);
+      }
+      done += run;
+    }
+    if self.swap_bytes.is_some() {
+      for value in buf.iter_mut() {
+        *value = self.swapped(*value);
+      }
+    }
+    Ok(())
+  }
+
+  /// Moves the elements at `[from, to)` so they start at `dest`, like `slice::copy_within`. The
+  /// destination slots must be backed by loaded chunks and already journaled.
+  fn copy_within(&self, from: u64, to: u64, dest: u64) -> Result<(), MmapVecDequeError> {
+    if from >= to || from == dest {
+      return Ok(());
+    }
+    let count = to - from;
+    let chunks = self.chunks.lock();
+    let copy = |i: u64| -> Result<(), MmapVecDequeError> {
+      let (src_chunk, src_elem) = self.global_to_local(from + i);
+      let (dest_chunk, dest_elem) = self.global_to_local(dest + i);
+      if !chunks.contains_key(&src_chunk) || !chunks.contains_key(&dest_chunk) {
+        return Err(MmapVecDequeError::IndexOutOfRange);
+      }
+      unsafe {
+        let src = (chunks[&src_chunk].mmap.as_ptr() as *const T).add(src_elem);
+        let dst = (chunks[&dest_chunk].mmap.as_ptr() as *mut T).add(dest_elem);
+        ptr::write(dst, ptr::read(src));
+      }
+      Ok(())
+    };
+    // Copy in the direction that never overwrites an element before it was moved.
+    if dest < from {
+      (0..count).try_for_each(copy)?;
+    } else {
+      (0..count).rev().try_for_each(copy)?;
+    }
+    drop(chunks);
+    self.mark_written(dest, dest + count);
     Ok(())
   }
 
@@ -254,17 +975,37 @@ impl<T: Copy> MmapVecDeque<T> {
     let element_size = meta.element_size;
     drop(meta);
 
-    if chunk_idx >= chunks.len() {
+    if !chunks.contains_key(&chunk_idx) {
       return Err(MmapVecDequeError::IndexOutOfRange);
     }
-    let mmap = &chunks[chunk_idx].mmap;
+    let mmap = &chunks[&chunk_idx].mmap;
     let ptr = mmap.as_ptr();
+    // Aligned, as `check_element` makes sure.
     unsafe {
       let elem_ptr = ptr.add(elem_idx * element_size) as *const T;
-      Ok(ptr::read(elem_ptr))
+      Ok(self.swapped(ptr::read(elem_ptr)))
+    }
+  }
+
+  /// Converts an element between the byte order it is stored in and the native one.
+  fn swapped(&self, value: T) -> T {
+    match self.swap_bytes {
+      Some(swap_bytes) => swap_bytes.swap_value(value),
+      None => value,
     }
   }
 
+  /// Fails for portable deques on big-endian machines, whose elements are stored little-endian
+  /// and so can't be borrowed from the mappings.
+  fn check_borrowable(&self) -> Result<(), MmapVecDequeError> {
+    if self.swap_bytes.is_some() {
+      return Err(MmapVecDequeError::Other(
+        "elements of a portable deque can't be borrowed on a big-endian machine".to_string(),
+      ));
+    }
+    Ok(())
+  }
+
   pub fn len(&self) -> usize {
     let meta = self.meta.lock();
     meta.len()
@@ -275,21 +1016,22 @@ impl<T: Copy> MmapVecDeque<T> {
   }
 
   pub fn push_back(&mut self, value: T) -> Result<(), MmapVecDequeError> {
-    let mut meta = self.meta.lock();
-    let pos = meta.end;
-    meta.end += 1;
-    drop(meta);
-
-    self.write_element(pos, value)
+    let pos = self.meta.lock().end;
+    // Map the slot before changing anything, so a failure (e.g. the quota) leaves the deque as it was.
+    self.ensure_capacity_for(pos)?;
+    self.make_room(1, true)?;
+    self.write_element(pos, value)?;
+    self.meta.lock().end = pos + 1;
+    Ok(())
   }
 
   pub fn push_front(&mut self, value: T) -> Result<(), MmapVecDequeError> {
-    let mut meta = self.meta.lock();
-    meta.start = meta.start.checked_sub(1).ok_or_else(|| MmapVecDequeError::Other("Start index underflow".to_string()))?;
-    let pos = meta.start;
-    drop(meta);
-
-    self.write_element(pos, value)
+    let pos = self.meta.lock().start.checked_sub(1).ok_or_else(|| MmapVecDequeError::Other("Start index underflow".to_string()))?;
+    self.ensure_capacity_for(pos)?;
+    self.make_room(1, false)?;
+    self.write_element(pos, value)?;
+    self.meta.lock().start = pos;
+    Ok(())
   }
 
   pub fn pop_back(&mut self) -> Result<Option<T>, MmapVecDequeError> {
@@ -318,19 +1060,206 @@ impl<T: Copy> MmapVecDeque<T> {
     Ok(Some(val))
   }
 
-  pub fn front(&self) -> Option<T> where T: Clone {
-    if self.is_empty() {
-      return None;
+  /// Appends all of `values` to the back, copying whole runs per chunk.
+  pub fn extend_back_from_slice(&mut self, values: &[T]) -> Result<(), MmapVecDequeError> {
+    // Values that would be evicted right away by the ones after them are never written.
+    let values = match self.evicting_capacity() {
+      Some(capacity) => &values[values.len().saturating_sub(capacity)..],
+      None => values,
+    };
+    if values.is_empty() {
+      return Ok(());
     }
-    self.get(0)
+    let end = self.meta.lock().end;
+    let new_end = end + values.len() as u64;
+    self.ensure_capacity_for(end)?;
+    self.ensure_capacity_for(new_end - 1)?;
+    self.make_room(values.len() as u64, true)?;
+    self.journal_range(end, new_end)?;
+    self.store_slice(end, values)?;
+    self.meta.lock().end = new_end;
+    Ok(())
   }
 
-  pub fn back(&self) -> Option<T> where T: Clone {
-    let l = self.len();
-    if l == 0 {
-      return None;
+  /// Prepends all of `values` to the front, keeping their order: afterwards `values[0]` is the
+  /// front element.
+  pub fn extend_front_from_slice(&mut self, values: &[T]) -> Result<(), MmapVecDequeError> {
+    let values = match self.evicting_capacity() {
+      Some(capacity) => &values[..values.len().min(capacity)],
+      None => values,
+    };
+    if values.is_empty() {
+      return Ok(());
     }
-    self.get(l - 1)
+    let start = self.meta.lock().start;
+    let new_start = start
+      .checked_sub(values.len() as u64)
+      .ok_or_else(|| MmapVecDequeError::Other("Start index underflow".to_string()))?;
+    self.ensure_capacity_for(start - 1)?;
+    self.ensure_capacity_for(new_start)?;
+    self.make_room(values.len() as u64, false)?;
+    self.journal_range(new_start, start)?;
+    self.store_slice(new_start, values)?;
+    self.meta.lock().start = new_start;
+    Ok(())
+  }
+
+  /// Pops up to `buf.len()` elements from the front into `buf`, in order. Returns how many
+  /// elements were popped.
+  pub fn pop_front_into(&mut self, buf: &mut [T]) -> Result<usize, MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let start = meta.start;
+    let count = meta.len().min(buf.len());
+    drop(meta);
+    self.load_slice(start, &mut buf[..count])?;
+    self.meta.lock().start = start + count as u64;
+    Ok(count)
+  }
+
+  /// Pops up to `buf.len()` elements from the back into `buf`. The popped elements keep their
+  /// order in the deque, so the former back element ends up last. Returns how many elements were
+  /// popped.
+  pub fn pop_back_into(&mut self, buf: &mut [T]) -> Result<usize, MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let end = meta.end;
+    let count = meta.len().min(buf.len());
+    drop(meta);
+    let new_end = end - count as u64;
+    self.load_slice(new_end, &mut buf[..count])?;
+    self.meta.lock().end = new_end;
+    Ok(count)
+  }
+
+  /// Inserts `value` at `index`, shifting the elements on whichever side of `index` is shorter.
+  /// Fails with `IndexOutOfRange` if `index` is greater than `len()`.
+  ///
+  /// On a full bounded deque that evicts, the front element is evicted first, like for
+  /// `push_back`; inserting at index 0 then leaves the deque unchanged.
+  pub fn insert(&mut self, index: usize, value: T) -> Result<(), MmapVecDequeError> {
+    if index > self.len() {
+      return Err(MmapVecDequeError::IndexOutOfRange);
+    }
+    let meta = self.meta.lock();
+    let full = meta.capacity.is_some_and(|capacity| meta.len() as u64 >= capacity);
+    let evicts = meta.overflow == Overflow::Evict;
+    let (start, end) = (meta.start, meta.end);
+    drop(meta);
+    if index == 0 && full && evicts {
+      // The value would be evicted right away along with the front element it goes in front of.
+      return Ok(());
+    }
+    // Map the slot the deque grows into before evicting anything, so a failure (e.g. the quota)
+    // leaves the deque as it was.
+    let evicted = (full && evicts) as usize;
+    let (after_index, after_len) = (index - evicted, self.len() - evicted);
+    if after_index < after_len - after_index {
+      let new_start = (start + evicted as u64).checked_sub(1).ok_or_else(|| MmapVecDequeError::Other("Start index underflow".to_string()))?;
+      self.ensure_capacity_for(new_start)?;
+    } else {
+      self.ensure_capacity_for(end)?;
+    }
+    let before = self.len();
+    self.make_room(1, true)?;
+    let index = index - (before - self.len());
+    let meta = self.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    let len = meta.len();
+    drop(meta);
+    let pos = start + index as u64;
+
+    if index < len - index {
+      let new_start = start.checked_sub(1).ok_or_else(|| MmapVecDequeError::Other("Start index underflow".to_string()))?;
+      self.ensure_capacity_for(new_start)?;
+      self.journal_range(new_start, pos)?;
+      self.copy_within(start, pos, new_start)?;
+      self.meta.lock().start = new_start;
+      self.store_element(pos - 1, value)
+    } else {
+      self.ensure_capacity_for(end)?;
+      self.journal_range(pos, end + 1)?;
+      self.copy_within(pos, end, pos + 1)?;
+      self.meta.lock().end = end + 1;
+      self.store_element(pos, value)
+    }
+  }
+
+  /// Removes and returns the element at `index`, shifting the elements on whichever side of
+  /// `index` is shorter. Returns `None` if `index` is out of bounds.
+  pub fn remove(&mut self, index: usize) -> Result<Option<T>, MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    let len = meta.len();
+    drop(meta);
+    if index >= len {
+      return Ok(None);
+    }
+    let pos = start + index as u64;
+    let val = self.read_element(pos)?;
+
+    if index < len - index - 1 {
+      self.journal_range(start + 1, pos + 1)?;
+      self.copy_within(start, pos, start + 1)?;
+      self.meta.lock().start = start + 1;
+    } else {
+      self.journal_range(pos, end - 1)?;
+      self.copy_within(pos + 1, end, pos)?;
+      self.meta.lock().end = end - 1;
+    }
+    Ok(Some(val))
+  }
+
+  /// Removes and returns the element at `index`, replacing it with the first element. This
+  /// doesn't preserve ordering, but is O(1). Returns `None` if `index` is out of bounds.
+  pub fn swap_remove_front(&mut self, index: usize) -> Result<Option<T>, MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let start = meta.start;
+    let len = meta.len();
+    drop(meta);
+    if index >= len {
+      return Ok(None);
+    }
+    let pos = start + index as u64;
+    let val = self.read_element(pos)?;
+    if pos != start {
+      let front = self.read_element(start)?;
+      self.write_element(pos, front)?;
+    }
+    self.meta.lock().start = start + 1;
+    Ok(Some(val))
+  }
+
+  /// Removes and returns the element at `index`, replacing it with the last element. This
+  /// doesn't preserve ordering, but is O(1). Returns `None` if `index` is out of bounds.
+  pub fn swap_remove_back(&mut self, index: usize) -> Result<Option<T>, MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    drop(meta);
+    if index as u64 >= end - start {
+      return Ok(None);
+    }
+    let pos = start + index as u64;
+    let val = self.read_element(pos)?;
+    if pos != end - 1 {
+      let back = self.read_element(end - 1)?;
+      self.write_element(pos, back)?;
+    }
+    self.meta.lock().end = end - 1;
+    Ok(Some(val))
+  }
+
+  pub fn front(&self) -> Option<T> where T: Clone {
+    if self.is_empty() {
+      return None;
+    }
+    self.get(0)
+  }
+
+  pub fn back(&self) -> Option<T> where T: Clone {
+    let l = self.len();
+    if l == 0 {
+      return None;
+    }
+    self.get(l - 1)
   }
 
   pub fn clear(&mut self) -> Result<(), MmapVecDequeError> {
@@ -342,6 +1271,87 @@ impl<T: Copy> MmapVecDeque<T> {
     Ok(())
   }
 
+  /// Shortens the deque to its first `len` elements. Does nothing if it isn't longer than `len`.
+  pub fn truncate(&mut self, len: usize) {
+    let mut meta = self.meta.lock();
+    if len < meta.len() {
+      meta.end = meta.start + len as u64;
+    }
+  }
+
+  /// Shortens the deque to its last `len` elements. Does nothing if it isn't longer than `len`.
+  pub fn truncate_front(&mut self, len: usize) {
+    let mut meta = self.meta.lock();
+    if len < meta.len() {
+      meta.start = meta.end - len as u64;
+    }
+  }
+
+  /// Removes the elements in `range` and returns them as an iterator, which reads them from the
+  /// mappings as it goes. The elements are removed when the iterator is dropped, closing the gap
+  /// by shifting whichever side of it is shorter; if it is leaked, the deque is left unchanged.
+  /// Fails with `IndexOutOfRange` if `range` is out of bounds.
+  pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Result<Drain<'_, T>, MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    drop(meta);
+    let (from, to) = self.global_range(range)?;
+    // Journal the slots the shift will overwrite now, so that dropping the iterator can't fail.
+    let count = to - from;
+    if from - start < end - to {
+      self.journal_range(start + count, to)?;
+    } else {
+      self.journal_range(from, end - count)?;
+    }
+    Ok(Drain { deque: self, from, to, front: from, back: to })
+  }
+
+  /// Keeps only the elements for which `f` returns `true`, preserving their order. `f` is called
+  /// once per element, front to back.
+  pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) -> Result<(), MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    drop(meta);
+    let mut keep = Vec::with_capacity((end - start) as usize);
+    for index in start..end {
+      keep.push(f(&self.read_element(index)?));
+    }
+    let Some(first_removed) = keep.iter().position(|k| !k) else {
+      return Ok(());
+    };
+
+    // Only the slots from the first removed element up to the new end are rewritten.
+    let first = start + first_removed as u64;
+    let new_end = start + keep.iter().filter(|k| **k).count() as u64;
+    self.journal_range(first, new_end)?;
+    let kept = (first..end).zip(&keep[first_removed..]).filter(|(_, k)| **k).map(|(read, _)| read);
+    for (write, read) in (first..).zip(kept) {
+      let val = self.read_element(read)?;
+      self.store_element(write, val)?;
+    }
+    self.meta.lock().end = new_end;
+    Ok(())
+  }
+
+  /// Like `retain`, but `f` may also modify the elements it keeps.
+  pub fn retain_mut<F: FnMut(&mut T) -> bool>(&mut self, mut f: F) -> Result<(), MmapVecDequeError> {
+    let meta = self.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    drop(meta);
+    // Any element may be modified, so journal the whole range up front, like `iter_mut`.
+    self.journal_range(start, end)?;
+    let mut guard = RetainGuard { deque: self, read: start, write: start, end };
+    while guard.read < end {
+      let mut val = guard.deque.read_element(guard.read)?;
+      if f(&mut val) {
+        guard.deque.store_element(guard.write, val)?;
+        guard.write += 1;
+      }
+      guard.read += 1;
+    }
+    Ok(())
+  }
+
   pub fn get(&self, index: usize) -> Option<T> where T: Clone {
     let meta = self.meta.lock();
     if index >= meta.len() {
@@ -350,160 +1360,437 @@ impl<T: Copy> MmapVecDeque<T> {
     let global_idx = meta.start + index as u64;
     drop(meta);
 
-    match self.read_element(global_idx) {
-      Ok(val) => Some(val),
-      Err(_) => None,
+    self.read_element(global_idx).ok()
+  }
+
+  /// Returns a reference to the element at `index` in the mapping, or `None` if out of bounds.
+  ///
+  /// # Panics
+  ///
+  /// Panics for portable deques on big-endian machines, which store their elements in the other
+  /// byte order; use `get` there.
+  pub fn get_ref(&self, index: usize) -> Option<&T> {
+    if let Err(e) = self.check_borrowable() {
+      panic!("{}", e);
+    }
+    let meta = self.meta.lock();
+    if index >= meta.len() {
+      return None;
     }
+    let global_idx = meta.start + index as u64;
+    let chunk_size = meta.chunk_size as u64;
+    drop(meta);
+
+    let ptr = self.chunk_ptr(global_idx / chunk_size);
+    Some(unsafe { &*ptr.add((global_idx % chunk_size) as usize) })
   }
 
-  pub fn get_mut(&mut self, index: usize) -> Result<Option<T>, MmapVecDequeError> where T: Clone {
+  /// Returns a mutable reference to the element at `index` in the mapping, or `None` if out of
+  /// bounds. The element is journaled first and changes through the reference are persisted by
+  /// the next `commit()`. Fails for portable deques on big-endian machines, see `get_ref`.
+  pub fn get_mut(&mut self, index: usize) -> Result<Option<&mut T>, MmapVecDequeError> {
+    self.check_borrowable()?;
     let meta = self.meta.lock();
     if index >= meta.len() {
       return Ok(None);
     }
     let global_idx = meta.start + index as u64;
+    let chunk_size = meta.chunk_size as u64;
     drop(meta);
 
-    let mut chunks = self.chunks.lock();
-    let (chunk_idx, elem_idx) = self.global_to_local(global_idx);
-    if chunk_idx >= chunks.len() {
-      return Err(MmapVecDequeError::IndexOutOfRange);
+    self.journal_range(global_idx, global_idx + 1)?;
+    self.mark_written(global_idx, global_idx + 1);
+    let ptr = self.chunk_ptr(global_idx / chunk_size);
+    Ok(Some(unsafe { &mut *ptr.add((global_idx % chunk_size) as usize) }))
+  }
+
+  pub fn front_mut(&mut self) -> Result<Option<&mut T>, MmapVecDequeError> {
+    self.get_mut(0)
+  }
+
+  pub fn back_mut(&mut self) -> Result<Option<&mut T>, MmapVecDequeError> {
+    match self.len().checked_sub(1) {
+      Some(index) => self.get_mut(index),
+      None => Ok(None),
     }
-    let mmap = &mut chunks[chunk_idx].mmap;
-    let ptr = mmap.as_mut_ptr();
-    unsafe {
-      let elem_ptr = ptr.add(elem_idx * size_of::<T>()) as *mut T;
-      let value = ptr::read(elem_ptr);
-      Ok(Some(value))
+  }
+
+  /// Replaces the element at `index`. Fails with `IndexOutOfRange` if `index` is out of bounds.
+  pub fn set(&mut self, index: usize, value: T) -> Result<(), MmapVecDequeError> {
+    let meta = self.meta.lock();
+    if index >= meta.len() {
+      return Err(MmapVecDequeError::IndexOutOfRange);
     }
+    let global_idx = meta.start + index as u64;
+    drop(meta);
+    self.write_element(global_idx, value)
   }
 
+
   pub fn commit(&self) -> Result<(), MmapVecDequeError> {
     if *self.dirty.lock() {
       self.flush_all_chunks()?;
       *self.dirty.lock() = false;
     }
 
-    let meta = self.meta.lock();
-    Self::atomic_write_metadata(&self.dir, &*meta)?;
+    let (start, end) = {
+      let meta = self.meta.lock();
+      (meta.start, meta.end)
+    };
+    let checksums = self.compute_checksums(start, end)?;
+
+    let mut meta = self.meta.lock();
+    meta.checksums = checksums;
+    Self::atomic_write_metadata(&self.dir, &meta)?;
+    *self.committed.lock() = (meta.start, meta.end);
     drop(meta);
 
-    self.maybe_shrink_chunks()?;
+    // Removing the journal is what makes in-place overwrites of committed slots final.
+    if self.journal.lock().take().is_some() {
+      Journal::remove(&self.dir)?;
+    }
+    self.journaled.lock().clear();
+    let protected = self.shared.publish(start, end);
+    self.shared.committed.notify_all();
+    #[cfg(feature = "tokio")]
+    self.shared.committed_async.notify_waiters();
+    self.counter.bump();
+    let previous_protected = std::mem::replace(&mut *self.protected.lock(), protected);
+
+    // Chunks released by this commit are no longer referenced by the metadata on disk. Files
+    // still visible to readers are kept, so that a writer reusing them writes to the same file.
+    let released = self.maybe_shrink_chunks()?;
+    if self.meta.lock().ring().is_some() {
+      // Bounded deques keep their chunk files for reuse.
+      return Ok(());
+    }
+    let retained = self.retained_chunks();
+    if previous_protected != protected {
+      let generation = self.meta.lock().generation;
+      Self::remove_stale_chunk_files(&self.dir, retained, None, generation)?;
+    } else {
+      let mut removed = false;
+      for index in released.into_iter().filter(|i| *i < retained.0 || *i > retained.1) {
+        match fs::remove_file(self.chunk_path(index)) {
+          Ok(()) => removed = true,
+          Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+          Err(e) => return Err(e.into()),
+        }
+      }
+      if removed {
+        let dir_file = OpenOptions::new().read(true).open(&self.dir)?;
+        dir_file.sync_all()?;
+      }
+    }
     Ok(())
   }
 
-  fn maybe_shrink_chunks(&self) -> Result<(), MmapVecDequeError> {
+  /// Chunks whose files must be kept: the committed ones and the ones readers may still read.
+  fn retained_chunks(&self) -> (u64, u64) {
     let meta = self.meta.lock();
     let chunk_size = meta.chunk_size as u64;
-    let start_chunk = meta.start / chunk_size;
-    let end_chunk = if meta.end == meta.start {
-      start_chunk
+    drop(meta);
+    let (committed_start, committed_end) = *self.committed.lock();
+    let (protected_start, protected_end) = *self.protected.lock();
+    let (first, last) = chunk_range(committed_start, committed_end, chunk_size);
+    if protected_start < protected_end {
+      let (p_first, p_last) = chunk_range(protected_start, protected_end, chunk_size);
+      (first.min(p_first), last.max(p_last))
     } else {
-      (meta.end - 1) / chunk_size
-    };
+      (first, last)
+    }
+  }
+
+  /// Discards all changes made since the last `commit()`.
+  ///
+  /// The start and end indices are reloaded from `metadata.bin` and any committed
+  /// elements overwritten in the meantime are restored to their committed values.
+  pub fn rollback(&mut self) -> Result<(), MmapVecDequeError> {
+    let stored = Metadata::read(&self.dir)?;
+    let mut meta = self.meta.lock();
+    meta.start = stored.start;
+    meta.end = stored.end;
+    meta.checksums = stored.checksums.clone();
     drop(meta);
+    self.load_chunks()?;
 
-    let mut chunks = self.chunks.lock();
-    let base = *self.base_chunk.lock();
-    let mut current_len = chunks.len() as u64;
-    if current_len == 0 {
-      return Ok(());
+    let mut restored = HashSet::new();
+    if let Some(contents) = Journal::read(&self.dir, stored.element_size)? {
+      for (index, bytes) in contents.records {
+        self.store_bytes(index, &bytes)?;
+        restored.insert(index);
+      }
+      self.flush_all_chunks()?;
+      *self.journal.lock() = None;
+      Journal::remove(&self.dir)?;
     }
+    // Slots outside the committed range that older readers still show keep the discarded writes,
+    // so readers keep needing the values saved for them.
+    self.shared.originals.write().latest().retain(|index, _| !restored.contains(index));
+    self.journaled.lock().clear();
+    self.written_chunks.lock().clear();
+    *self.committed.lock() = (stored.start, stored.end);
+
+    // Chunks created for the discarded changes are not needed anymore.
+    let meta = self.meta.lock();
+    let (ring, generation) = (meta.ring(), meta.generation);
+    drop(meta);
+    Self::remove_stale_chunk_files(&self.dir, self.retained_chunks(), ring, generation)?;
+    Ok(())
+  }
 
-    let mut current_start_chunk = base;
+  /// Unmaps chunks outside the live range and returns their file numbers.
+  fn maybe_shrink_chunks(&self) -> Result<Vec<u64>, MmapVecDequeError> {
+    let (start_chunk, end_chunk) = self.meta.lock().chunk_range();
+    let live: HashSet<u64> = (start_chunk..=end_chunk).map(|chunk| self.file_number(chunk)).collect();
 
-    // Remove front chunks if not needed
-    while chunks.len() > 1 && current_start_chunk < start_chunk {
-      chunks.remove(0);
-      current_start_chunk += 1;
+    let mut chunks = self.chunks.lock();
+    let released: Vec<u64> = chunks.keys().copied().filter(|file_number| !live.contains(file_number)).collect();
+    for file_number in &released {
+      chunks.remove(file_number);
     }
+    Ok(released)
+  }
 
-    // Remove end chunks if not needed
-    while chunks.len() > 1 {
-      current_len = chunks.len() as u64;
-      let current_end_chunk = current_start_chunk + current_len - 1;
-      if current_end_chunk > end_chunk {
-        chunks.pop();
-      } else {
-        break;
-      }
-    }
+  /// Returns an iterator over copies of the elements, front to back.
+  ///
+  /// The iterator walks the chunk mappings lazily, without allocating.
+  pub fn iter(&self) -> Iter<'_, T> {
+    let meta = self.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    drop(meta);
+    Iter { raw: RawIter::new(self, start, end), _marker: PhantomData }
+  }
 
-    *self.base_chunk.lock() = current_start_chunk;
+  /// Returns an iterator of mutable references to the elements.
+  ///
+  /// Committed elements are journaled before the iterator is handed out, so changes made
+  /// through it stay atomic with respect to the next `commit()`.
+  ///
+  /// # Panics
+  ///
+  /// Panics if writing the journal fails, or for portable deques on big-endian machines (see
+  /// `get_ref`); use `range_mut(..)` to handle errors.
+  pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+    self.range_mut(..).expect("failed to journal MmapVecDeque elements")
+  }
 
-    Ok(())
+  /// Returns an iterator over copies of the elements in `range`. Fails with `IndexOutOfRange`
+  /// if `range` is out of bounds.
+  pub fn range<R: RangeBounds<usize>>(&self, range: R) -> Result<Iter<'_, T>, MmapVecDequeError> {
+    let (from, to) = self.global_range(range)?;
+    Ok(Iter { raw: RawIter::new(self, from, to), _marker: PhantomData })
   }
 
-  pub fn iter(&self) -> Iter<'_, T> {
-    let len = self.len();
-    let mut pointers = Vec::with_capacity(len);
+  /// Returns an iterator of mutable references to the elements in `range`, journaling them like
+  /// `iter_mut()`. Fails with `IndexOutOfRange` if `range` is out of bounds, and for portable
+  /// deques on big-endian machines (see `get_ref`).
+  pub fn range_mut<R: RangeBounds<usize>>(&mut self, range: R) -> Result<IterMut<'_, T>, MmapVecDequeError> {
+    self.check_borrowable()?;
+    let (from, to) = self.global_range(range)?;
+    self.journal_range(from, to)?;
+    self.mark_written(from, to);
+    Ok(IterMut { raw: RawIter::new(self, from, to), _marker: PhantomData })
+  }
 
+  /// Returns an iterator over the elements as contiguous slices of the mappings, one per chunk,
+  /// front to back. Like `VecDeque::as_slices`, but with as many slices as there are chunks.
+  ///
+  /// # Panics
+  ///
+  /// Panics for portable deques on big-endian machines, see `get_ref`.
+  pub fn chunks_slices(&self) -> ChunkSlices<'_, T> {
+    if let Err(e) = self.check_borrowable() {
+      panic!("{}", e);
+    }
+    let meta = self.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    drop(meta);
+    ChunkSlices { raw: RawIter::new(self, start, end) }
+  }
+
+  /// Converts a range of positions into global indices, checking it against the length.
+  fn global_range<R: RangeBounds<usize>>(&self, range: R) -> Result<(u64, u64), MmapVecDequeError> {
     let meta = self.meta.lock();
     let start = meta.start;
-    let chunk_size = meta.chunk_size as u64;
-    let element_size = meta.element_size;
+    let len = meta.len();
     drop(meta);
+    let from = match range.start_bound() {
+      Bound::Included(&i) => i,
+      Bound::Excluded(&i) => i.checked_add(1).ok_or(MmapVecDequeError::IndexOutOfRange)?,
+      Bound::Unbounded => 0,
+    };
+    let to = match range.end_bound() {
+      Bound::Included(&i) => i.checked_add(1).ok_or(MmapVecDequeError::IndexOutOfRange)?,
+      Bound::Excluded(&i) => i,
+      Bound::Unbounded => len,
+    };
+    if from > to || to > len {
+      return Err(MmapVecDequeError::IndexOutOfRange);
+    }
+    Ok((start + from as u64, start + to as u64))
+  }
 
-    let base = *self.base_chunk.lock();
-    let chunks = self.chunks.lock();
-    for i in 0..len {
-      let global_idx = start + i as u64;
-      let chunk_idx = ((global_idx / chunk_size) - base) as usize;
-      let elem_idx = (global_idx % chunk_size) as usize;
-      let mmap = &chunks[chunk_idx].mmap;
-      let ptr = mmap.as_ptr();
-      let elem_ptr = unsafe { ptr.add(elem_idx * element_size) as *const T };
-      pointers.push(elem_ptr);
+  /// Base address of the mapping of `chunk`, which must be loaded. Mappings stay at the same
+  /// address until their chunk is released, which requires `&mut self` or a commit that moved
+  /// the start or end past it.
+  fn chunk_ptr(&self, chunk: u64) -> *mut T {
+    let file_number = self.file_number(chunk);
+    let mut chunks = self.chunks.lock();
+    chunks.get_mut(&file_number).unwrap().mmap.as_mut_ptr() as *mut T
+  }
+}
+
+/// Global indices in `[lo, hi)` stored in the same slot as `index`: just `index` itself, or in a
+/// bounded deque every index a multiple of `lap` elements away.
+fn slot_aliases(index: u64, lo: u64, hi: u64, lap: Option<u64>) -> std::iter::StepBy<std::ops::Range<u64>> {
+  match lap {
+    Some(lap) => {
+      let first = lo + (index as i128 - lo as i128).rem_euclid(lap as i128) as u64;
+      (first..hi.max(first)).step_by(lap as usize)
     }
-    drop(chunks);
+    None if (lo..hi).contains(&index) => (index..index + 1).step_by(1),
+    None => (0..0).step_by(1),
+  }
+}
 
-    Iter {
-      pointers,
-      index: 0,
-      len,
-      _marker: PhantomData
+/// Appends the elements of an iterator in batches with `extend_back_from_slice`.
+///
+/// # Panics
+///
+/// Panics if writing to the chunk files fails; use `extend_back_from_slice` to handle errors.
+impl<T: Copy> Extend<T> for MmapVecDeque<T> {
+  fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+    let batch_size = self.meta.lock().chunk_size;
+    let mut iter = iter.into_iter();
+    let mut batch = Vec::with_capacity(batch_size.min(iter.size_hint().0.max(1)));
+    loop {
+      batch.clear();
+      batch.extend(iter.by_ref().take(batch_size));
+      if batch.is_empty() {
+        return;
+      }
+      self.extend_back_from_slice(&batch).expect("failed to extend MmapVecDeque");
     }
   }
+}
 
-  pub fn iter_mut(&mut self) -> IterMut<'_, T> {
-    let len = self.len();
+/// Cursor over the slots `[front, back)` shared by `Iter` and `IterMut`. The mapping of the
+/// chunk at each end is cached, so the chunk list is only locked when crossing into a new chunk.
+struct RawIter<'a, T: Copy> {
+  deque: &'a MmapVecDeque<T>,
+  front: u64,
+  back: u64,
+  chunk_size: u64,
+  front_chunk: Option<(u64, *mut T)>,
+  back_chunk: Option<(u64, *mut T)>,
+}
 
-    let meta = self.meta.lock();
-    let start = meta.start;
-    let chunk_size = meta.chunk_size as u64;
-    let element_size = meta.element_size;
-    drop(meta);
+impl<'a, T: Copy> RawIter<'a, T> {
+  fn new(deque: &'a MmapVecDeque<T>, front: u64, back: u64) -> Self {
+    let chunk_size = deque.meta.lock().chunk_size as u64;
+    RawIter { deque, front, back, chunk_size, front_chunk: None, back_chunk: None }
+  }
 
-    let base = *self.base_chunk.lock();
-    // Lock chunks for the entire iteration.
-    // The guard is stored in the iterator to keep it alive.
-    let mut chunks_guard = self.chunks.lock();
+  fn slot(&self, cached: &mut Option<(u64, *mut T)>, index: u64) -> *mut T {
+    let chunk = index / self.chunk_size;
+    let base = match *cached {
+      Some((cached_chunk, ptr)) if cached_chunk == chunk => ptr,
+      _ => {
+        let ptr = self.deque.chunk_ptr(chunk);
+        *cached = Some((chunk, ptr));
+        ptr
+      }
+    };
+    unsafe { base.add((index % self.chunk_size) as usize) }
+  }
 
-    let mut pointers = Vec::with_capacity(len);
-    for i in 0..len {
-      let global_idx = start + i as u64;
-      let chunk_idx = ((global_idx / chunk_size) - base) as usize;
-      let elem_idx = (global_idx % chunk_size) as usize;
-      let mmap = &mut chunks_guard[chunk_idx].mmap;
-      let ptr = mmap.as_mut_ptr();
-      let elem_ptr = unsafe { ptr.add(elem_idx * element_size) as *mut T };
-      pointers.push(elem_ptr);
+  fn len(&self) -> usize {
+    (self.back - self.front) as usize
+  }
+
+  fn next(&mut self) -> Option<*mut T> {
+    if self.front >= self.back {
+      return None;
     }
+    let mut cached = self.front_chunk.take();
+    let slot = self.slot(&mut cached, self.front);
+    self.front_chunk = cached;
+    self.front += 1;
+    Some(slot)
+  }
 
-    IterMut {
-      pointers,
-      index: 0,
-      len,
-      _marker: PhantomData,
-      _chunks_guard: chunks_guard,
+  fn next_back(&mut self) -> Option<*mut T> {
+    if self.front >= self.back {
+      return None;
+    }
+    self.back -= 1;
+    let mut cached = self.back_chunk.take();
+    let slot = self.slot(&mut cached, self.back);
+    self.back_chunk = cached;
+    Some(slot)
+  }
+
+  fn nth(&mut self, n: usize) -> Option<*mut T> {
+    self.front = self.front.saturating_add(n as u64).min(self.back);
+    self.next()
+  }
+
+  fn nth_back(&mut self, n: usize) -> Option<*mut T> {
+    self.back = self.back.saturating_sub(n as u64).max(self.front);
+    self.next_back()
+  }
+
+  /// Takes the rest of the chunk at the front, up to `back`, as a pointer and length.
+  fn next_run(&mut self) -> Option<(*mut T, usize)> {
+    if self.front >= self.back {
+      return None;
+    }
+    let chunk_end = (self.front / self.chunk_size + 1) * self.chunk_size;
+    let len = (chunk_end.min(self.back) - self.front) as usize;
+    let ptr = self.next()?;
+    self.front += len as u64 - 1;
+    Some((ptr, len))
+  }
+
+  /// Takes the rest of the chunk at the back, down to `front`, as a pointer and length.
+  fn next_back_run(&mut self) -> Option<(*mut T, usize)> {
+    if self.front >= self.back {
+      return None;
     }
+    let chunk_start = ((self.back - 1) / self.chunk_size * self.chunk_size).max(self.front);
+    let len = (self.back - chunk_start) as usize;
+    self.back = chunk_start + 1;
+    let ptr = self.next_back()?;
+    Some((ptr, len))
+  }
+}
+
+/// Iterator over the elements of a `MmapVecDeque` as one slice per chunk, returned by
+/// `chunks_slices()`.
+pub struct ChunkSlices<'a, T: Copy> {
+  raw: RawIter<'a, T>,
+}
+
+impl<'a, T: Copy> Iterator for ChunkSlices<'a, T> {
+  type Item = &'a [T];
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.raw.next_run().map(|(ptr, len)| unsafe { std::slice::from_raw_parts(ptr, len) })
   }
 }
 
+impl<'a, T: Copy> DoubleEndedIterator for ChunkSlices<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.raw.next_back_run().map(|(ptr, len)| unsafe { std::slice::from_raw_parts(ptr, len) })
+  }
+}
+
+impl<'a, T: Copy> FusedIterator for ChunkSlices<'a, T> {}
+
+/// Iterator over copies of the elements of a `MmapVecDeque`, returned by `iter()`.
 pub struct Iter<'a, T: Copy> {
-  pointers: Vec<*const T>,
-  index: usize,
-  len: usize,
+  raw: RawIter<'a, T>,
   _marker: PhantomData<&'a T>,
 }
 
@@ -511,39 +1798,138 @@ impl<'a, T: Copy> Iterator for Iter<'a, T> {
   type Item = T;
 
   fn next(&mut self) -> Option<Self::Item> {
-    if self.index < self.len {
-      let ptr = self.pointers[self.index];
-      self.index += 1;
-      unsafe { Some(*ptr) }
-    } else {
-      None
-    }
+    self.raw.next().map(|ptr| self.raw.deque.swapped(unsafe { *ptr }))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.raw.len(), Some(self.raw.len()))
+  }
+
+  fn nth(&mut self, n: usize) -> Option<Self::Item> {
+    self.raw.nth(n).map(|ptr| self.raw.deque.swapped(unsafe { *ptr }))
+  }
+}
+
+impl<'a, T: Copy> DoubleEndedIterator for Iter<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.raw.next_back().map(|ptr| self.raw.deque.swapped(unsafe { *ptr }))
+  }
+
+  fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+    self.raw.nth_back(n).map(|ptr| self.raw.deque.swapped(unsafe { *ptr }))
   }
 }
 
 impl<'a, T: Copy> ExactSizeIterator for Iter<'a, T> {}
 
+impl<'a, T: Copy> FusedIterator for Iter<'a, T> {}
+
+/// Iterator of mutable references to the elements of a `MmapVecDeque`, returned by `iter_mut()`.
 pub struct IterMut<'a, T: Copy> {
-  pointers: Vec<*mut T>,
-  index: usize,
-  len: usize,
+  raw: RawIter<'a, T>,
   _marker: PhantomData<&'a mut T>,
-  // Keep the chunks guard so memory remains valid and locked.
-  _chunks_guard: parking_lot::MutexGuard<'a, Vec<Chunk>>,
 }
 
 impl<'a, T: Copy> Iterator for IterMut<'a, T> {
   type Item = &'a mut T;
 
   fn next(&mut self) -> Option<Self::Item> {
-    if self.index < self.len {
-      let ptr = self.pointers[self.index];
-      self.index += 1;
-      unsafe { Some(&mut *ptr) }
+    self.raw.next().map(|ptr| unsafe { &mut *ptr })
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    (self.raw.len(), Some(self.raw.len()))
+  }
+
+  fn nth(&mut self, n: usize) -> Option<Self::Item> {
+    self.raw.nth(n).map(|ptr| unsafe { &mut *ptr })
+  }
+}
+
+impl<'a, T: Copy> DoubleEndedIterator for IterMut<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.raw.next_back().map(|ptr| unsafe { &mut *ptr })
+  }
+
+  fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+    self.raw.nth_back(n).map(|ptr| unsafe { &mut *ptr })
+  }
+}
+
+impl<'a, T: Copy> ExactSizeIterator for IterMut<'a, T> {}
+
+impl<'a, T: Copy> FusedIterator for IterMut<'a, T> {}
+
+/// Iterator over the elements removed by `MmapVecDeque::drain`.
+pub struct Drain<'a, T: Copy> {
+  deque: &'a mut MmapVecDeque<T>,
+  from: u64,
+  to: u64,
+  front: u64,
+  back: u64,
+}
+
+impl<'a, T: Copy> Iterator for Drain<'a, T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.front >= self.back {
+      return None;
+    }
+    self.front += 1;
+    Some(self.deque.read_element(self.front - 1).expect("drained elements are mapped"))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let len = (self.back - self.front) as usize;
+    (len, Some(len))
+  }
+}
+
+impl<'a, T: Copy> DoubleEndedIterator for Drain<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.front >= self.back {
+      return None;
+    }
+    self.back -= 1;
+    Some(self.deque.read_element(self.back).expect("drained elements are mapped"))
+  }
+}
+
+impl<'a, T: Copy> ExactSizeIterator for Drain<'a, T> {}
+
+impl<'a, T: Copy> FusedIterator for Drain<'a, T> {}
+
+impl<'a, T: Copy> Drop for Drain<'a, T> {
+  fn drop(&mut self) {
+    let meta = self.deque.meta.lock();
+    let (start, end) = (meta.start, meta.end);
+    drop(meta);
+    // `drain` journaled the overwritten slots, and they all lie in mapped chunks.
+    let count = self.to - self.from;
+    if self.from - start < end - self.to {
+      self.deque.copy_within(start, self.from, start + count).expect("drained elements are mapped");
+      self.deque.meta.lock().start = start + count;
     } else {
-      None
+      self.deque.copy_within(self.to, end, self.from).expect("drained elements are mapped");
+      self.deque.meta.lock().end = end - count;
     }
   }
 }
 
-impl<'a, T: Copy> ExactSizeIterator for IterMut<'a, T> {}
+/// Ends `retain_mut`, also when `f` panics or a write fails: the elements not visited yet are
+/// moved down behind the ones kept so far, and the end is set after them.
+struct RetainGuard<'a, T: Copy> {
+  deque: &'a MmapVecDeque<T>,
+  read: u64,
+  write: u64,
+  end: u64,
+}
+
+impl<'a, T: Copy> Drop for RetainGuard<'a, T> {
+  fn drop(&mut self) {
+    if self.deque.copy_within(self.read, self.end, self.write).is_ok() {
+      self.deque.meta.lock().end = self.write + (self.end - self.read);
+    }
+  }
+}
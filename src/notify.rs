@@ -0,0 +1,148 @@
+use crate::error::MmapVecDequeError;
+use memmap2::{Mmap, MmapMut, MmapOptions};
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const NOTIFY_FILE: &str = "notify.bin";
+const NOTIFY_LEN: usize = 16;
+
+/// Commit counter shared between processes through a small mapped file.
+///
+/// Layout: `u64` number of completed commits, then a `u32` futex word that changes along with it.
+/// The writer bumps both after every commit and wakes every process waiting on the futex word.
+/// Where futexes aren't available, waiters poll the counter instead.
+pub(crate) enum CommitCounter {
+  Writer(MmapMut),
+  Reader(Mmap),
+}
+
+impl CommitCounter {
+  /// Opens the counter of `dir` for writing, creating it if needed.
+  pub(crate) fn create(dir: &Path) -> Result<Self, MmapVecDequeError> {
+    let file = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(false)
+      .open(dir.join(NOTIFY_FILE))?;
+    if file.metadata()?.len() < NOTIFY_LEN as u64 {
+      file.set_len(NOTIFY_LEN as u64)?;
+    }
+    let mmap = unsafe { MmapOptions::new().len(NOTIFY_LEN).map_mut(&file)? };
+    Ok(CommitCounter::Writer(mmap))
+  }
+
+  /// Maps the counter of `dir` read-only. Returns `None` until a writer has created it.
+  pub(crate) fn open(dir: &Path) -> Result<Option<Self>, MmapVecDequeError> {
+    let file = match File::open(dir.join(NOTIFY_FILE)) {
+      Ok(file) => file,
+      Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(e.into()),
+    };
+    if file.metadata()?.len() < NOTIFY_LEN as u64 {
+      // Still being created by a writer.
+      return Ok(None);
+    }
+    let mmap = unsafe { MmapOptions::new().len(NOTIFY_LEN).map(&file)? };
+    Ok(Some(CommitCounter::Reader(mmap)))
+  }
+
+  fn ptr(&self) -> *const u8 {
+    match self {
+      CommitCounter::Writer(mmap) => mmap.as_ptr(),
+      CommitCounter::Reader(mmap) => mmap.as_ptr(),
+    }
+  }
+
+  fn seq_word(&self) -> &AtomicU64 {
+    // The mapping is page aligned and lives as long as `self`.
+    unsafe { &*(self.ptr() as *const AtomicU64) }
+  }
+
+  fn futex_word(&self) -> &AtomicU32 {
+    unsafe { &*(self.ptr().add(8) as *const AtomicU32) }
+  }
+
+  /// Number of commits completed in this directory.
+  pub(crate) fn seq(&self) -> u64 {
+    self.seq_word().load(Ordering::Acquire)
+  }
+
+  /// Records a completed commit and wakes every waiter. Returns the new sequence number.
+  pub(crate) fn bump(&self) -> u64 {
+    let seq = self.seq_word().fetch_add(1, Ordering::AcqRel) + 1;
+    self.futex_word().fetch_add(1, Ordering::Release);
+    futex_wake(self.futex_word());
+    seq
+  }
+
+  /// Blocks until the sequence number exceeds `since_seq` or `timeout` elapses.
+  pub(crate) fn wait(&self, since_seq: u64, timeout: Duration) -> Option<u64> {
+    let deadline = Instant::now() + timeout;
+    loop {
+      // Read the futex word first: a bump between the two loads then fails the futex wait.
+      let word = self.futex_word().load(Ordering::Acquire);
+      let seq = self.seq();
+      if seq > since_seq {
+        return Some(seq);
+      }
+      let remaining = deadline.checked_duration_since(Instant::now()).filter(|d| !d.is_zero())?;
+      futex_wait(self.futex_word(), word, remaining);
+    }
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn futex_wait(word: &AtomicU32, expected: u32, timeout: Duration) {
+  let timeout = libc::timespec {
+    tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+    tv_nsec: timeout.subsec_nanos() as libc::c_long,
+  };
+  // Not FUTEX_PRIVATE_FLAG: the word is shared with other processes through the file.
+  unsafe {
+    libc::syscall(
+      libc::SYS_futex,
+      word.as_ptr(),
+      libc::FUTEX_WAIT,
+      expected,
+      &timeout as *const libc::timespec,
+      std::ptr::null::<u32>(),
+      0u32,
+    );
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn futex_wake(word: &AtomicU32) {
+  unsafe {
+    libc::syscall(
+      libc::SYS_futex,
+      word.as_ptr(),
+      libc::FUTEX_WAKE,
+      i32::MAX,
+      std::ptr::null::<libc::timespec>(),
+      std::ptr::null::<u32>(),
+      0u32,
+    );
+  }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn futex_wait(word: &AtomicU32, expected: u32, timeout: Duration) {
+  let deadline = Instant::now() + timeout;
+  while word.load(Ordering::Acquire) == expected {
+    let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+      return;
+    };
+    std::thread::sleep(remaining.min(POLL_INTERVAL));
+  }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn futex_wake(_word: &AtomicU32) {}
+
+/// How often waiters check for a counter that doesn't exist yet, or without futex support.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(5);
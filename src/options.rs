@@ -0,0 +1,172 @@
+use crate::error::MmapVecDequeError;
+use crate::mmap_vecdeque::MmapVecDeque;
+use crate::persistent::PersistentType;
+use crate::portable::{Portable, SwapBytes};
+use crate::reader::Reader;
+use serde::{Serialize, Deserialize};
+use std::path::Path;
+
+/// What pushing onto a full bounded deque does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Overflow {
+  /// Evict elements from the opposite end to make room.
+  Evict,
+  /// Fail with `MmapVecDequeError::Full`.
+  Fail,
+}
+
+/// Settings for opening or creating a deque, like `std::fs::OpenOptions`.
+///
+/// ```no_run
+/// # use mmap_vecdeque::{DequeOptions, MmapVecDeque, Overflow};
+/// # use std::path::Path;
+/// let mut options = DequeOptions::new();
+/// options.chunk_size(4096).bounded(100_000, Overflow::Evict);
+/// // Safety: any 8 bytes are a valid `u64`.
+/// let history: MmapVecDeque<u64> = unsafe { options.open(Path::new("history")) }?;
+/// # Ok::<(), mmap_vecdeque::MmapVecDequeError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DequeOptions {
+  pub(crate) chunk_size: Option<usize>,
+  pub(crate) capacity: Option<(u64, Overflow)>,
+  pub(crate) quota: Option<u64>,
+  pub(crate) type_name: Option<(String, u32)>,
+  pub(crate) accepted_type_names: Vec<String>,
+  pub(crate) swap_bytes: Option<SwapBytes>,
+  pub(crate) verify_on_open: bool,
+}
+
+impl DequeOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Number of elements per chunk file. Must match the stored chunk size of an existing deque.
+  pub fn chunk_size(&mut self, chunk_size: usize) -> &mut Self {
+    self.chunk_size = Some(chunk_size);
+    self
+  }
+
+  /// Creates a ring buffer holding at most `capacity` elements, which reuses its chunk files
+  /// circularly instead of creating new ones. The setting is stored with the deque; an existing
+  /// deque must have been created with the same capacity, and takes the overflow policy given
+  /// here. A `Reader` that isn't refreshed while later commits wrap around the ring may show
+  /// the elements that replaced its evicted ones.
+  pub fn bounded(&mut self, capacity: usize, overflow: Overflow) -> &mut Self {
+    self.capacity = Some((capacity as u64, overflow));
+    self
+  }
+
+  /// Limits the bytes the deque may occupy on disk, as reported by `disk_usage()`. A push that
+  /// needs a new chunk file which would take usage past the limit fails with
+  /// `MmapVecDequeError::QuotaExceeded`. Commits are never refused, so the metadata and journal
+  /// they write can still go slightly past it. The quota is not stored with the deque.
+  pub fn quota(&mut self, bytes: u64) -> &mut Self {
+    self.quota = Some(bytes);
+    self
+  }
+
+  /// Identifies elements by `T::TYPE_NAME` and `T::LAYOUT_VERSION` instead of
+  /// `std::any::type_name`. Use it with the same `T` the deque is opened with.
+  pub fn persistent<T: PersistentType>(&mut self) -> &mut Self {
+    self.type_name = Some((T::TYPE_NAME.to_string(), T::LAYOUT_VERSION));
+    self
+  }
+
+  /// Also accepts an existing deque whose elements are stored under `type_name`, e.g. the
+  /// `std::any::type_name` it was created with before the type became a `PersistentType`, or
+  /// before it was renamed. Opening it for writing stores the current name in its place.
+  pub fn accept_type_name(&mut self, type_name: &str) -> &mut Self {
+    self.accepted_type_names.push(type_name.to_string());
+    self
+  }
+
+  /// Makes the deque portable, so that it can be copied to machines of another byte order or
+  /// pointer width. Elements are stored little-endian. On big-endian machines, deques and readers
+  /// opened with this setting convert each element as they read or write it, and borrowing
+  /// elements from the mappings (`get_ref`, `get_mut`, `range_mut`, `chunks_slices`) fails;
+  /// readers opened without it, and `migrate`, fail with `MmapVecDequeError::PlatformMismatch`
+  /// there. An existing deque becomes portable once opened for writing with this
+  /// setting, which first converts its chunk files to little-endian if needed, as safely as
+  /// `migrate` does. Use it with the same `T` the deque is opened with; opening with an element
+  /// type of another size fails.
+  pub fn portable<T: Portable>(&mut self) -> &mut Self {
+    self.swap_bytes = Some(SwapBytes::new::<T>());
+    self
+  }
+
+  /// Verifies every committed chunk against its checksum when the deque is opened, which reads
+  /// the whole deque. By default, only the first and last chunk are verified on open; see
+  /// `MmapVecDeque::verify`.
+  pub fn verify_on_open(&mut self) -> &mut Self {
+    self.verify_on_open = true;
+    self
+  }
+
+  /// The converter given to `portable`, checked against elements of type `T`.
+  pub(crate) fn portable_swap<T>(&self) -> Result<Option<SwapBytes>, MmapVecDequeError> {
+    self.swap_bytes.map(SwapBytes::check::<T>).transpose()
+  }
+
+  /// The name and layout version elements of type `T` are stored under.
+  pub(crate) fn type_identity<T>(&self) -> (String, u32) {
+    self.type_name.clone().unwrap_or_else(|| (std::any::type_name::<T>().to_string(), 0))
+  }
+
+  /// Opens the deque in `dir`, creating the directory and an empty deque if needed.
+  ///
+  /// # Safety
+  ///
+  /// See `MmapVecDeque::open_or_create`.
+  pub unsafe fn open<T: Copy>(&self, dir: &Path) -> Result<MmapVecDeque<T>, MmapVecDequeError> {
+    MmapVecDeque::open_with(dir, self)
+  }
+
+  /// Same as `open`, restricted to types for which any bytes are a valid value.
+  #[cfg(feature = "bytemuck")]
+  pub fn open_pod<T: bytemuck::Pod>(&self, dir: &Path) -> Result<MmapVecDeque<T>, MmapVecDequeError> {
+    MmapVecDeque::open_with(dir, self)
+  }
+
+  /// Like `MmapVecDeque::migrate`, with the chunk size and type identity of the migrated deque
+  /// taken from these options. Names given to `accept_type_name` are also accepted for the stored
+  /// `Old` type, e.g. to migrate from a `PersistentType` or a type that was renamed.
+  ///
+  /// # Safety
+  ///
+  /// See `MmapVecDeque::migrate`.
+  pub unsafe fn migrate<Old: Copy, New: Copy>(&self, dir: &Path, f: impl FnMut(Old) -> New) -> Result<(), MmapVecDequeError> {
+    MmapVecDeque::<New>::migrate_with(dir, self, f)
+  }
+
+  /// Like `MmapVecDeque::open_read_only`, honoring the type identity settings.
+  ///
+  /// # Safety
+  ///
+  /// See `MmapVecDeque::open_or_create`.
+  pub unsafe fn open_read_only<T: Copy>(&self, dir: &Path) -> Result<Reader<T>, MmapVecDequeError> {
+    Reader::open(dir, false, self)
+  }
+
+  /// Same as `open_read_only`, restricted to types for which any bytes are a valid value.
+  #[cfg(feature = "bytemuck")]
+  pub fn open_read_only_pod<T: bytemuck::Pod>(&self, dir: &Path) -> Result<Reader<T>, MmapVecDequeError> {
+    Reader::open(dir, false, self)
+  }
+
+  /// Like `MmapVecDeque::open_follower`, honoring the type identity settings.
+  ///
+  /// # Safety
+  ///
+  /// See `MmapVecDeque::open_or_create`.
+  pub unsafe fn open_follower<T: Copy>(&self, dir: &Path) -> Result<Reader<T>, MmapVecDequeError> {
+    Reader::open(dir, true, self)
+  }
+
+  /// Same as `open_follower`, restricted to types for which any bytes are a valid value.
+  #[cfg(feature = "bytemuck")]
+  pub fn open_follower_pod<T: bytemuck::Pod>(&self, dir: &Path) -> Result<Reader<T>, MmapVecDequeError> {
+    Reader::open(dir, true, self)
+  }
+}
@@ -0,0 +1,39 @@
+/// An element type with a stable identity, stored in the deque metadata instead of
+/// `std::any::type_name`, which can change with the compiler version or when the type is moved or
+/// renamed.
+///
+/// Implement it with the `persistent_type!` macro and open deques with
+/// `MmapVecDeque::open_persistent` or `DequeOptions::persistent`.
+pub trait PersistentType: Copy {
+  /// Identifier stored with the deque. It must never change once deques of the type exist.
+  const TYPE_NAME: &'static str;
+
+  /// Version of the element layout. Bump it whenever the layout changes, so that existing deques
+  /// fail to open with `LayoutVersionMismatch` instead of being misread.
+  const LAYOUT_VERSION: u32 = 0;
+}
+
+/// Implements `PersistentType` for a type, with an optional layout version (0 by default).
+///
+/// ```
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Sample {
+///   timestamp: u64,
+///   value: f64,
+/// }
+///
+/// mmap_vecdeque::persistent_type!(Sample, "acme.sample", 1);
+/// ```
+#[macro_export]
+macro_rules! persistent_type {
+  ($ty:ty, $name:expr) => {
+    $crate::persistent_type!($ty, $name, 0);
+  };
+  ($ty:ty, $name:expr, $version:expr) => {
+    impl $crate::PersistentType for $ty {
+      const TYPE_NAME: &'static str = $name;
+      const LAYOUT_VERSION: u32 = $version;
+    }
+  };
+}
@@ -0,0 +1,112 @@
+use crate::error::MmapVecDequeError;
+use std::mem::size_of;
+use std::{ptr, slice};
+
+/// An element type with the same layout on every platform apart from its byte order, such as the
+/// fixed-width integer and float types and arrays of them. Deques of portable types can be copied
+/// to machines of another byte order or pointer width; see `DequeOptions::portable`.
+///
+/// Implement it for structs of portable fields by swapping each field, taking care that the
+/// struct is `#[repr(C)]` without padding that depends on the platform:
+///
+/// ```
+/// use mmap_vecdeque::Portable;
+///
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Sample {
+///   timestamp: u64,
+///   value: f64,
+/// }
+///
+/// impl Portable for Sample {
+///   fn swap_bytes(self) -> Self {
+///     Sample { timestamp: self.timestamp.swap_bytes(), value: self.value.swap_bytes() }
+///   }
+/// }
+/// ```
+pub trait Portable: Copy {
+  /// Returns the value with the byte order of each of its fields reversed.
+  fn swap_bytes(self) -> Self;
+}
+
+macro_rules! portable_integers {
+  ($($ty:ty),*) => {
+    $(
+      impl Portable for $ty {
+        fn swap_bytes(self) -> Self {
+          <$ty>::swap_bytes(self)
+        }
+      }
+    )*
+  };
+}
+
+portable_integers!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Portable for f32 {
+  fn swap_bytes(self) -> Self {
+    f32::from_bits(self.to_bits().swap_bytes())
+  }
+}
+
+impl Portable for f64 {
+  fn swap_bytes(self) -> Self {
+    f64::from_bits(self.to_bits().swap_bytes())
+  }
+}
+
+impl<T: Portable, const N: usize> Portable for [T; N] {
+  fn swap_bytes(self) -> Self {
+    self.map(T::swap_bytes)
+  }
+}
+
+/// Reverses the byte order of elements of the type given to `DequeOptions::portable`.
+/// `DequeOptions` keeps it as a plain function, so that deques of any element type can convert
+/// their chunk files.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SwapBytes {
+  swap: fn(&mut [u8]),
+  element_size: usize, // Size of the portable type
+}
+
+impl SwapBytes {
+  pub(crate) fn new<T: Portable>() -> Self {
+    SwapBytes { swap: swap_element::<T>, element_size: size_of::<T>() }
+  }
+
+  /// Checks that the portable type has the size of the element type `T` the deque is opened
+  /// with, since its bytes are converted as such.
+  pub(crate) fn check<T>(self) -> Result<Self, MmapVecDequeError> {
+    if self.element_size != size_of::<T>() {
+      return Err(MmapVecDequeError::Other(format!(
+        "portable type of {} bytes given for elements of {} bytes",
+        self.element_size,
+        size_of::<T>()
+      )));
+    }
+    Ok(self)
+  }
+
+  /// Reverses the byte order of the element stored in `bytes`.
+  pub(crate) fn swap(self, bytes: &mut [u8]) {
+    (self.swap)(bytes)
+  }
+
+  /// Returns `value` with its byte order reversed. `T` must be the element type this converter
+  /// was checked against.
+  pub(crate) fn swap_value<T: Copy>(self, mut value: T) -> T {
+    self.swap(unsafe { slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, size_of::<T>()) });
+    value
+  }
+}
+
+/// Reverses the byte order of the `T` stored in `bytes`.
+fn swap_element<T: Portable>(bytes: &mut [u8]) {
+  assert_eq!(bytes.len(), size_of::<T>());
+  unsafe {
+    let value = ptr::read_unaligned(bytes.as_ptr() as *const T);
+    ptr::write_unaligned(bytes.as_mut_ptr() as *mut T, value.swap_bytes());
+  }
+}
@@ -0,0 +1,559 @@
+use crate::error::MmapVecDequeError;
+use crate::journal::Journal;
+use crate::lock::DirLock;
+use crate::metadata::{check_element, chunk_file, chunk_range, live_bounds, Metadata};
+use crate::notify::{CommitCounter, POLL_INTERVAL};
+use crate::options::DequeOptions;
+use crate::portable::SwapBytes;
+use memmap2::{Mmap, MmapOptions};
+use parking_lot::{Condvar, Mutex, RwLock};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::{Duration, Instant};
+use std::{ptr, slice, mem::{size_of, MaybeUninit}, thread};
+
+/// A committed `[start, end)` range, numbered by the commit that produced it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Snapshot {
+  pub(crate) start: u64,
+  pub(crate) end: u64,
+  pub(crate) seq: u64,
+}
+
+/// Values of overwritten slots as of each commit that readers may still show.
+///
+/// The layer of commit `seq` holds the value each slot had in that commit, for the slots
+/// overwritten between it and the next one. A reader showing commit `seq` looks a slot up in that
+/// layer and then in every newer one, since a slot that wasn't overwritten after `seq` kept its
+/// value until a later overwrite saved it. The latest layer receives the slots overwritten since
+/// the latest commit; older layers are dropped once no reader shows their commit.
+pub(crate) struct Originals<T> {
+  layers: BTreeMap<u64, HashMap<u64, T>>,
+}
+
+impl<T: Copy> Originals<T> {
+  fn new(seq: u64, values: HashMap<u64, T>) -> Self {
+    Originals { layers: BTreeMap::from([(seq, values)]) }
+  }
+
+  /// Slots overwritten since the latest commit, keyed by global index.
+  pub(crate) fn latest(&mut self) -> &mut HashMap<u64, T> {
+    self.layers.values_mut().next_back().expect("the latest commit always has a layer")
+  }
+
+  /// The value `index` had in commit `seq`, if it was overwritten since.
+  fn get(&self, seq: u64, index: u64) -> Option<T> {
+    self.layers.range(seq..).find_map(|(_, values)| values.get(&index).copied())
+  }
+}
+
+/// State shared between a deque and the readers it handed out.
+///
+/// A writer must insert the committed value of a slot into `originals` before it starts
+/// overwriting that slot, and readers look a slot up in `originals` before reading the mapping,
+/// holding the read lock for both. So readers never observe uncommitted data, and never read a
+/// slot while it is being written.
+pub(crate) struct Shared<T> {
+  dir: PathBuf,
+  chunk_size: u64,
+  element_size: usize,
+  ring: Option<u64>, // Number of chunk files of a bounded deque
+  generation: u64, // Generation of the chunk files, see `Metadata::generation`
+  _lock: Option<DirLock>, // The writer's exclusive lock, or the shared lock of readers opened with `open_read_only`
+  snapshot: RwLock<Snapshot>,
+  pub(crate) originals: RwLock<Originals<T>>,
+  views: Mutex<Vec<Weak<View>>>, // Snapshots currently held by readers
+  pub(crate) committed: Condvar, // Notified after every commit
+  #[cfg(feature = "tokio")]
+  pub(crate) committed_async: tokio::sync::Notify, // Notified after every commit, for async waiters
+  follow: bool, // Whether the snapshot is reloaded from disk, for readers opened with `open_follower`
+  counter: OnceLock<CommitCounter>, // The directory's commit counter, mapped once a writer created it
+  swap_bytes: Option<SwapBytes>, // Converts elements of a portable deque stored in the other byte order
+}
+
+impl<T: Copy> Shared<T> {
+  pub(crate) fn new(dir: &Path, meta: &Metadata, lock: Option<DirLock>, originals: HashMap<u64, T>) -> Self {
+    Shared {
+      dir: dir.to_path_buf(),
+      chunk_size: meta.chunk_size as u64,
+      element_size: meta.element_size,
+      ring: meta.ring(),
+      generation: meta.generation,
+      _lock: lock,
+      snapshot: RwLock::new(Snapshot { start: meta.start, end: meta.end, seq: 0 }),
+      originals: RwLock::new(Originals::new(0, originals)),
+      views: Mutex::new(Vec::new()),
+      committed: Condvar::new(),
+      #[cfg(feature = "tokio")]
+      committed_async: tokio::sync::Notify::new(),
+      follow: false,
+      counter: OnceLock::new(),
+      swap_bytes: None,
+    }
+  }
+
+  /// Shared state reading elements through `swap_bytes` if they are stored in the other byte
+  /// order.
+  pub(crate) fn opened(dir: &Path, meta: &Metadata, lock: Option<DirLock>, originals: HashMap<u64, T>, swap_bytes: Option<SwapBytes>) -> Self {
+    let mut shared = Shared::new(dir, meta, lock, originals);
+    shared.swap_bytes = swap_bytes;
+    shared
+  }
+
+  /// Shared state of a reader following commits made by another handle, as of commit `seq`.
+  fn follower(dir: &Path, meta: &Metadata, seq: u64, originals: HashMap<u64, T>, swap_bytes: Option<SwapBytes>) -> Self {
+    let mut shared = Shared::opened(dir, meta, None, HashMap::new(), swap_bytes);
+    shared.snapshot.get_mut().seq = seq;
+    shared.originals = RwLock::new(Originals::new(seq, originals));
+    shared.follow = true;
+    shared
+  }
+
+  pub(crate) fn snapshot(&self) -> Snapshot {
+    *self.snapshot.read()
+  }
+
+  /// Whether any reader holds a snapshot, i.e. whether `originals` must be maintained.
+  pub(crate) fn has_readers(&self) -> bool {
+    !self.views.lock().is_empty()
+  }
+
+  /// Publishes a new commit to readers and returns the range of global indices that readers may
+  /// still read: the new committed range plus every snapshot a reader still holds.
+  pub(crate) fn publish(&self, start: u64, end: u64) -> (u64, u64) {
+    let mut originals = self.originals.write();
+    let mut snapshot = self.snapshot.write();
+    let seq = snapshot.seq + 1;
+    *snapshot = Snapshot { start, end, seq };
+
+    let mut views = self.views.lock();
+    views.retain(|view| view.strong_count() > 0);
+    let mut protected = (start, end);
+    let mut oldest = seq;
+    for view in views.iter().filter_map(Weak::upgrade) {
+      oldest = oldest.min(view.snapshot.seq);
+      if view.snapshot.start < view.snapshot.end {
+        protected.0 = protected.0.min(view.snapshot.start);
+        protected.1 = protected.1.max(view.snapshot.end);
+      }
+    }
+    originals.layers.insert(seq, HashMap::new());
+    originals.layers = originals.layers.split_off(&oldest);
+    protected
+  }
+
+  fn counter(&self) -> Result<Option<&CommitCounter>, MmapVecDequeError> {
+    if self.counter.get().is_none() {
+      if let Some(counter) = CommitCounter::open(&self.dir)? {
+        let _ = self.counter.set(counter);
+      }
+    }
+    Ok(self.counter.get())
+  }
+
+  fn commit_seq(&self) -> Result<u64, MmapVecDequeError> {
+    Ok(self.counter()?.map_or(0, CommitCounter::seq))
+  }
+
+  fn wait_for_commit(&self, since_seq: u64, timeout: Duration) -> Result<Option<u64>, MmapVecDequeError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+      let remaining = deadline.saturating_duration_since(Instant::now());
+      if let Some(counter) = self.counter()? {
+        return Ok(counter.wait(since_seq, remaining));
+      }
+      // No writer has opened the directory yet.
+      if remaining.is_zero() {
+        return Ok(None);
+      }
+      thread::sleep(remaining.min(POLL_INTERVAL));
+    }
+  }
+
+  /// Replaces the snapshot and originals with the committed state on disk, as of commit `seq`.
+  fn reload(&self, seq: u64) -> Result<(), MmapVecDequeError> {
+    let Committed { meta, originals, .. } = load_committed::<T>(&self.dir, self.swap_bytes)?;
+    if meta.generation != self.generation {
+      return Err(MmapVecDequeError::Other("the deque was migrated; reopen it with its new element type".to_string()));
+    }
+    let mut current = self.originals.write();
+    let mut snapshot = self.snapshot.write();
+    *current = Originals::new(seq, originals);
+    *snapshot = Snapshot { start: meta.start, end: meta.end, seq };
+    Ok(())
+  }
+
+  /// Maps the chunks of the current snapshot, reusing the mappings of `previous` where possible.
+  fn map_view(&self, previous: Option<&View>) -> Result<Arc<View>, MmapVecDequeError> {
+    // Holding the snapshot lock keeps the writer from publishing a newer commit, and so from
+    // deleting the chunk files of this one, while they are being mapped.
+    let snapshot = self.snapshot.read();
+    let (first, _) = chunk_range(snapshot.start, snapshot.end, self.chunk_size);
+    let mut chunks = Vec::new();
+    if snapshot.start < snapshot.end {
+      let (_, last) = chunk_range(snapshot.start, snapshot.end, self.chunk_size);
+      for index in first..=last {
+        let reused = previous.and_then(|p| p.chunk(index)).cloned();
+        let mmap = match reused {
+          Some(mmap) => mmap,
+          None => Arc::new(self.map_chunk(index)?),
+        };
+        chunks.push(mmap);
+      }
+    }
+    // The view is registered before a newer commit can be published, so that the originals of
+    // its commit are kept.
+    let view = Arc::new(View { snapshot: *snapshot, base_chunk: first, chunks });
+    self.views.lock().push(Arc::downgrade(&view));
+    drop(snapshot);
+    Ok(view)
+  }
+
+  fn map_chunk(&self, index: u64) -> Result<Mmap, MmapVecDequeError> {
+    let chunk_byte_size = self.chunk_size as usize * self.element_size;
+    let file = File::open(chunk_file(&self.dir, index, self.ring, self.generation))?;
+    let file_len = file.metadata()?.len();
+    if file_len < chunk_byte_size as u64 {
+      return Err(MmapVecDequeError::Corruption {
+        chunk: Some(index),
+        details: format!("chunk file is {} bytes, expected {}", file_len, chunk_byte_size),
+      });
+    }
+    Ok(unsafe { MmapOptions::new().len(chunk_byte_size).map(&file)? })
+  }
+}
+
+/// Read-only mappings of the chunks of one snapshot.
+struct View {
+  snapshot: Snapshot,
+  base_chunk: u64,
+  chunks: Vec<Arc<Mmap>>,
+}
+
+impl View {
+  fn chunk(&self, index: u64) -> Option<&Arc<Mmap>> {
+    index.checked_sub(self.base_chunk).and_then(|i| self.chunks.get(i as usize))
+  }
+}
+
+/// Read-only handle showing the elements of one commit.
+///
+/// Readers are created by `MmapVecDeque::reader`, which shares the writer's state so the reader
+/// can be moved to another thread while the writer keeps appending, or by
+/// `MmapVecDeque::open_read_only`, which maps an existing directory read-only without creating or
+/// modifying any file (e.g. on a read-only mount), or by `MmapVecDeque::open_follower`, which reads
+/// the commits of a writer in another process. Cloning is cheap; clones share the mappings.
+///
+/// A reader never shows uncommitted changes. `refresh()` moves it to the latest commit. Until then
+/// it keeps showing the elements of its commit, even where later commits overwrote them in place
+/// (e.g. with `iter_mut`); the writer keeps their old values in memory for as long as a reader
+/// shows that commit. Readers opened with `open_follower` are the exception, see there.
+pub struct Reader<T: Copy> {
+  shared: Arc<Shared<T>>,
+  view: Arc<View>,
+}
+
+impl<T: Copy> Clone for Reader<T> {
+  fn clone(&self) -> Self {
+    Reader {
+      shared: self.shared.clone(),
+      view: self.view.clone(),
+    }
+  }
+}
+
+impl<T: Copy> Reader<T> {
+  pub(crate) fn new(shared: Arc<Shared<T>>) -> Result<Self, MmapVecDequeError> {
+    let view = shared.map_view(None)?;
+    Ok(Reader { shared, view })
+  }
+
+  pub(crate) fn open(dir: &Path, follow: bool, options: &DequeOptions) -> Result<Self, MmapVecDequeError> {
+    check_element::<T>()?;
+    if !dir.is_dir() {
+      return Err(MmapVecDequeError::Io(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("deque directory `{}` does not exist", dir.display()),
+      )));
+    }
+    if follow {
+      return Self::open_follower(dir, options);
+    }
+    let lock = DirLock::shared(dir)?;
+    let Committed { meta, originals, swap_bytes } = load_committed::<T>(dir, options.portable_swap::<T>()?)?;
+    meta.check_type::<T>(options)?;
+    let reader = Reader::new(Arc::new(Shared::opened(dir, &meta, lock, originals, swap_bytes)))?;
+    reader.verify(&meta.checksums, options.verify_on_open)?;
+    Ok(reader)
+  }
+
+  fn open_follower(dir: &Path, options: &DequeOptions) -> Result<Self, MmapVecDequeError> {
+    loop {
+      let seq = CommitCounter::open(dir)?.map_or(0, |counter| counter.seq());
+      let Committed { meta, originals, swap_bytes } = load_committed::<T>(dir, options.portable_swap::<T>()?)?;
+      meta.check_type::<T>(options)?;
+      // Checksums aren't verified: the writer may be overwriting slots in place meanwhile.
+      match Reader::new(Arc::new(Shared::follower(dir, &meta, seq, originals, swap_bytes))) {
+        Err(e) if is_released_chunk(&e) && CommitCounter::open(dir)?.map_or(0, |c| c.seq()) != seq => continue,
+        result => return result,
+      }
+    }
+  }
+
+  /// Verifies every chunk of the snapshot against `checksums`, or only the first and last one.
+  fn verify(&self, checksums: &[u32], all: bool) -> Result<(), MmapVecDequeError> {
+    let view = &self.view;
+    if view.chunks.is_empty() {
+      return Ok(());
+    }
+    if checksums.len() != view.chunks.len() {
+      return Err(MmapVecDequeError::Corruption {
+        chunk: None,
+        details: format!("metadata holds {} checksums for {} chunks", checksums.len(), view.chunks.len()),
+      });
+    }
+    let chunk_size = self.shared.chunk_size;
+    let element_size = size_of::<T>();
+    let originals = self.shared.originals.read();
+    for (i, expected) in checksums.iter().enumerate() {
+      if !all && i != 0 && i != checksums.len() - 1 {
+        continue;
+      }
+      let chunk = view.base_chunk + i as u64;
+      let (from, to) = live_bounds(view.snapshot.start, view.snapshot.end, chunk, chunk_size);
+      let offset = (from % chunk_size) as usize * element_size;
+      let mut bytes = view.chunks[i][offset..offset + (to - from) as usize * element_size].to_vec();
+      for index in from..to {
+        if let Some(original) = originals.get(view.snapshot.seq, index) {
+          let at = (index - from) as usize * element_size;
+          unsafe { ptr::copy_nonoverlapping(&original as *const T as *const u8, bytes.as_mut_ptr().add(at), element_size) };
+          if let Some(swap_bytes) = self.shared.swap_bytes {
+            // Checksums cover the elements as stored.
+            swap_bytes.swap(&mut bytes[at..at + element_size]);
+          }
+        }
+      }
+      let actual = crc32c::crc32c(&bytes);
+      if actual != *expected {
+        return Err(MmapVecDequeError::Corruption {
+          chunk: Some(chunk),
+          details: format!("checksum mismatch: expected {:08x}, found {:08x}", expected, actual),
+        });
+      }
+    }
+    Ok(())
+  }
+
+  /// Moves the reader to the latest commit. Returns whether there was a newer commit.
+  pub fn refresh(&mut self) -> Result<bool, MmapVecDequeError> {
+    if self.shared.follow {
+      return self.follow();
+    }
+    if self.shared.snapshot().seq == self.view.snapshot.seq {
+      return Ok(false);
+    }
+    self.view = self.shared.map_view(Some(&self.view))?;
+    Ok(true)
+  }
+
+  fn follow(&mut self) -> Result<bool, MmapVecDequeError> {
+    if self.shared.commit_seq()? == self.view.snapshot.seq {
+      return Ok(false);
+    }
+    loop {
+      // Read the counter before the metadata, so a commit racing with this reload is seen again.
+      let seq = self.shared.commit_seq()?;
+      self.shared.reload(seq)?;
+      match self.shared.map_view(Some(&self.view)) {
+        Err(e) if is_released_chunk(&e) && self.shared.commit_seq()? != seq => continue,
+        result => {
+          self.view = result?;
+          return Ok(true);
+        }
+      }
+    }
+  }
+
+  /// Global index range of the commit the reader shows.
+  #[cfg(feature = "tokio")]
+  pub(crate) fn range(&self) -> (u64, u64) {
+    (self.view.snapshot.start, self.view.snapshot.end)
+  }
+
+  /// Copies the elements at global indices `[from, to)`, which must be within `range()`.
+  #[cfg(feature = "tokio")]
+  pub(crate) fn read_range(&self, from: u64, to: u64) -> Vec<T> {
+    let mut values = Vec::with_capacity((to - from) as usize);
+    self.read_into(from, to, &mut values);
+    values
+  }
+
+  /// Number of commits completed in the deque's directory, by this or any other process.
+  pub fn commit_seq(&self) -> Result<u64, MmapVecDequeError> {
+    self.shared.commit_seq()
+  }
+
+  /// Blocks until a commit after `since_seq` (a value of `commit_seq()`) completes in any process,
+  /// or until `timeout` elapses. Returns the new commit sequence number, or `None` on timeout.
+  ///
+  /// The reader itself doesn't move; call `refresh()` to see the new commit.
+  pub fn wait_for_commit(&self, since_seq: u64, timeout: Duration) -> Result<Option<u64>, MmapVecDequeError> {
+    self.shared.wait_for_commit(since_seq, timeout)
+  }
+
+  /// Copies the elements at global indices `[from, to)` to `out`, locking the originals once.
+  fn read_into(&self, from: u64, to: u64, out: &mut impl Extend<T>) {
+    let originals = self.shared.originals.read();
+    out.extend((from..to).map(|index| self.read_locked(&originals, index)));
+  }
+
+  fn read(&self, index: u64) -> T {
+    self.read_locked(&self.shared.originals.read(), index)
+  }
+
+  fn read_locked(&self, originals: &Originals<T>, index: u64) -> T {
+    if let Some(original) = originals.get(self.view.snapshot.seq, index) {
+      return original;
+    }
+    let chunk_size = self.shared.chunk_size;
+    let mmap = &self.view.chunks[(index / chunk_size - self.view.base_chunk) as usize];
+    let offset = (index % chunk_size) as usize * size_of::<T>();
+    match self.shared.swap_bytes {
+      Some(swap_bytes) => read_swapped(&mmap[offset..offset + size_of::<T>()], swap_bytes),
+      None => unsafe { ptr::read(mmap.as_ptr().add(offset) as *const T) },
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    (self.view.snapshot.end - self.view.snapshot.start) as usize
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  pub fn get(&self, index: usize) -> Option<T> {
+    if index >= self.len() {
+      return None;
+    }
+    Some(self.read(self.view.snapshot.start + index as u64))
+  }
+
+  pub fn front(&self) -> Option<T> {
+    self.get(0)
+  }
+
+  pub fn back(&self) -> Option<T> {
+    self.len().checked_sub(1).and_then(|i| self.get(i))
+  }
+
+  pub fn iter(&self) -> ReaderIter<'_, T> {
+    ReaderIter {
+      reader: self,
+      front: self.view.snapshot.start,
+      back: self.view.snapshot.end,
+      head: VecDeque::new(),
+      tail: VecDeque::new(),
+    }
+  }
+}
+
+/// The latest commit in a deque directory, as seen by readers.
+struct Committed<T> {
+  meta: Metadata,
+  originals: HashMap<u64, T>, // Committed values of slots overwritten since
+  swap_bytes: Option<SwapBytes>, // Set if the elements are stored in the other byte order
+}
+
+/// Reads the committed metadata of `dir` and the committed values of slots overwritten since.
+/// `swap_bytes` is the converter of `DequeOptions::portable`, kept if the elements are stored in
+/// the other byte order; without a converter, such deques fail the platform check.
+fn load_committed<T: Copy>(dir: &Path, swap_bytes: Option<SwapBytes>) -> Result<Committed<T>, MmapVecDequeError> {
+  let meta = Metadata::read(dir)?;
+
+  // While a journal exists, the commit it was created after is the latest one; it holds the
+  // committed metadata and the committed values of the slots overwritten since.
+  let (meta, records) = match Journal::read(dir, meta.element_size)? {
+    Some(contents) if !contents.metadata.is_empty() => (Metadata::decode(&contents.metadata)?, contents.records),
+    _ => (meta, Vec::new()),
+  };
+  let convert = meta.check_platform(swap_bytes.is_some())?;
+  let swap_bytes = swap_bytes.filter(|_| convert);
+  let originals = records
+    .iter()
+    .map(|(index, bytes)| {
+      let value = match swap_bytes {
+        Some(swap_bytes) => read_swapped(bytes, swap_bytes),
+        None => unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) },
+      };
+      (*index, value)
+    })
+    .collect();
+  Ok(Committed { meta, originals, swap_bytes })
+}
+
+/// Reads a `T` stored in the other byte order from the start of `bytes`.
+fn read_swapped<T: Copy>(bytes: &[u8], swap_bytes: SwapBytes) -> T {
+  let mut value = MaybeUninit::<T>::zeroed();
+  unsafe {
+    let raw = slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>());
+    raw.copy_from_slice(&bytes[..size_of::<T>()]);
+    swap_bytes.swap(raw);
+    value.assume_init()
+  }
+}
+
+/// Whether mapping a snapshot failed because a chunk file is missing. Writers only delete chunk
+/// files after counting the commit that released them, so if the commit sequence number moved on
+/// meanwhile, the chunk was released by a later commit and the snapshot must be reloaded.
+fn is_released_chunk(error: &MmapVecDequeError) -> bool {
+  matches!(error, MmapVecDequeError::Io(e) if e.kind() == io::ErrorKind::NotFound)
+}
+
+/// Elements a `ReaderIter` copies out of the mapping at a time, holding the originals lock.
+const READ_BATCH: u64 = 256;
+
+/// Iterator over the elements of a `Reader`, copying them out of the mapping in batches.
+pub struct ReaderIter<'a, T: Copy> {
+  reader: &'a Reader<T>,
+  front: u64, // First element not copied yet
+  back: u64, // One past the last element not copied yet
+  head: VecDeque<T>, // Copied elements at `front`, read from the front
+  tail: VecDeque<T>, // Copied elements at `back`, read from the back
+}
+
+impl<'a, T: Copy> Iterator for ReaderIter<'a, T> {
+  type Item = T;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.head.is_empty() && self.front < self.back {
+      let to = self.back.min(self.front + READ_BATCH);
+      self.reader.read_into(self.front, to, &mut self.head);
+      self.front = to;
+    }
+    self.head.pop_front().or_else(|| self.tail.pop_front())
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    let len = (self.back - self.front) as usize + self.head.len() + self.tail.len();
+    (len, Some(len))
+  }
+}
+
+impl<'a, T: Copy> DoubleEndedIterator for ReaderIter<'a, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.tail.is_empty() && self.front < self.back {
+      let from = self.front.max(self.back.saturating_sub(READ_BATCH));
+      let mut batch = VecDeque::new();
+      self.reader.read_into(from, self.back, &mut batch);
+      self.tail = batch;
+      self.back = from;
+    }
+    self.tail.pop_back().or_else(|| self.head.pop_back())
+  }
+}
+
+impl<'a, T: Copy> ExactSizeIterator for ReaderIter<'a, T> {}
@@ -0,0 +1,128 @@
+use crate::bytes_deque::{BytesIter, MmapBytesDeque};
+use crate::error::MmapVecDequeError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// A durable deque of arbitrary serde types, with the same commit and rollback semantics as
+/// `MmapVecDeque`.
+///
+/// Elements are serialized with postcard and stored as the records of a `MmapBytesDeque`, so the
+/// on-disk layout is the same: length-prefixed records in segment files, ordered by an index of
+/// record locations.
+pub struct MmapSerdeDeque<T> {
+  inner: MmapBytesDeque,
+  _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> MmapSerdeDeque<T> {
+  /// Opens the deque in `dir`, creating it if needed. `chunk_size` is the number of records per
+  /// chunk of the index, as for `MmapVecDeque::open_or_create`.
+  pub fn open_or_create(dir: &Path, chunk_size: Option<usize>) -> Result<Self, MmapVecDequeError> {
+    Ok(MmapSerdeDeque { inner: MmapBytesDeque::open_or_create(dir, chunk_size)?, _marker: PhantomData })
+  }
+
+  pub fn len(&self) -> usize {
+    self.inner.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.inner.is_empty()
+  }
+
+  pub fn push_back(&mut self, value: T) -> Result<(), MmapVecDequeError> {
+    self.inner.push_back(&postcard::to_allocvec(&value)?)
+  }
+
+  pub fn push_front(&mut self, value: T) -> Result<(), MmapVecDequeError> {
+    self.inner.push_front(&postcard::to_allocvec(&value)?)
+  }
+
+  pub fn pop_back(&mut self) -> Result<Option<T>, MmapVecDequeError> {
+    let value = self.back()?;
+    if value.is_some() {
+      self.inner.pop_back()?;
+    }
+    Ok(value)
+  }
+
+  pub fn pop_front(&mut self) -> Result<Option<T>, MmapVecDequeError> {
+    let value = self.front()?;
+    if value.is_some() {
+      self.inner.pop_front()?;
+    }
+    Ok(value)
+  }
+
+  /// Reads and deserializes the element at `index`.
+  pub fn get(&self, index: usize) -> Result<Option<T>, MmapVecDequeError> {
+    self.inner.get(index)?.map(decode).transpose()
+  }
+
+  pub fn front(&self) -> Result<Option<T>, MmapVecDequeError> {
+    self.inner.front()?.map(decode).transpose()
+  }
+
+  pub fn back(&self) -> Result<Option<T>, MmapVecDequeError> {
+    self.inner.back()?.map(decode).transpose()
+  }
+
+  pub fn clear(&mut self) -> Result<(), MmapVecDequeError> {
+    self.inner.clear()
+  }
+
+  /// Iterates over the elements front to back, deserializing each one as it is reached.
+  pub fn iter(&self) -> SerdeIter<'_, T> {
+    SerdeIter { inner: self.inner.iter(), _marker: PhantomData }
+  }
+
+  /// Durably persists all changes since the last commit, then deletes the segments no element
+  /// refers to any more.
+  pub fn commit(&mut self) -> Result<(), MmapVecDequeError> {
+    self.inner.commit()
+  }
+
+  /// Discards all changes since the last commit.
+  pub fn rollback(&mut self) -> Result<(), MmapVecDequeError> {
+    self.inner.rollback()
+  }
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, MmapVecDequeError> {
+  Ok(postcard::from_bytes(bytes)?)
+}
+
+/// Iterator over the elements of a `MmapSerdeDeque`, yielding an error for records that can't be
+/// read or deserialized.
+pub struct SerdeIter<'a, T> {
+  inner: BytesIter<'a>,
+  _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Iterator for SerdeIter<'_, T> {
+  type Item = Result<T, MmapVecDequeError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.inner.next().map(|bytes| decode(bytes?))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.inner.size_hint()
+  }
+
+  fn nth(&mut self, n: usize) -> Option<Self::Item> {
+    self.inner.nth(n).map(|bytes| decode(bytes?))
+  }
+}
+
+impl<T: DeserializeOwned> DoubleEndedIterator for SerdeIter<'_, T> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.inner.next_back().map(|bytes| decode(bytes?))
+  }
+}
+
+impl<T: DeserializeOwned> ExactSizeIterator for SerdeIter<'_, T> {}
+
+impl<T: DeserializeOwned> FusedIterator for SerdeIter<'_, T> {}
@@ -0,0 +1,123 @@
+use crate::error::MmapVecDequeError;
+use crate::mmap_vecdeque::MmapVecDeque;
+use crate::reader::Shared;
+use parking_lot::{Mutex, MutexGuard};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A `MmapVecDeque` shared between threads, whose pops can wait for other threads to commit new
+/// elements. Cloning is cheap; clones share the same deque.
+///
+/// ```no_run
+/// # use mmap_vecdeque::{MmapVecDeque, SharedDeque};
+/// # use std::path::Path;
+/// // Safety: any 8 bytes are a valid `u64`.
+/// let queue = SharedDeque::new(unsafe { MmapVecDeque::<u64>::open_or_create(Path::new("queue"), None) }?);
+/// let producer = queue.clone();
+/// std::thread::spawn(move || {
+///   producer.lock().push_back(42)?;
+///   producer.commit()
+/// });
+/// assert_eq!(queue.pop_front_blocking()?, 42);
+/// # Ok::<(), mmap_vecdeque::MmapVecDequeError>(())
+/// ```
+pub struct SharedDeque<T: Copy> {
+  inner: Arc<Mutex<MmapVecDeque<T>>>,
+  shared: Arc<Shared<T>>,
+}
+
+impl<T: Copy> Clone for SharedDeque<T> {
+  fn clone(&self) -> Self {
+    SharedDeque {
+      inner: self.inner.clone(),
+      shared: self.shared.clone(),
+    }
+  }
+}
+
+impl<T: Copy> SharedDeque<T> {
+  pub fn new(deque: MmapVecDeque<T>) -> Self {
+    let shared = deque.shared().clone();
+    SharedDeque {
+      inner: Arc::new(Mutex::new(deque)),
+      shared,
+    }
+  }
+
+  /// Locks the deque for access from this thread.
+  pub fn lock(&self) -> SharedDequeGuard<'_, T> {
+    SharedDequeGuard { guard: self.inner.lock() }
+  }
+
+  /// Commits all changes, like `MmapVecDeque::commit`, waking up threads waiting to pop.
+  pub fn commit(&self) -> Result<(), MmapVecDequeError> {
+    self.inner.lock().commit()
+  }
+
+  /// Pops the front element, waiting for another thread to `commit()` new elements while the
+  /// deque is empty. The lock is released while waiting.
+  pub fn pop_front_blocking(&self) -> Result<T, MmapVecDequeError> {
+    let mut guard = self.inner.lock();
+    self.wait_until_not_empty(&mut guard, None);
+    Ok(guard.pop_front()?.expect("deque is not empty"))
+  }
+
+  /// Like `pop_front_blocking`, but gives up after `timeout` and returns `Ok(None)`.
+  pub fn pop_front_timeout(&self, timeout: Duration) -> Result<Option<T>, MmapVecDequeError> {
+    let mut guard = self.inner.lock();
+    self.wait_until_not_empty(&mut guard, Some(Instant::now() + timeout));
+    guard.pop_front()
+  }
+
+  /// Waits like `pop_front_blocking` until the deque is not empty, then pops up to `max` elements
+  /// from the front.
+  pub fn pop_front_batch_blocking(&self, max: usize) -> Result<Vec<T>, MmapVecDequeError> {
+    let mut batch = Vec::new();
+    if max == 0 {
+      return Ok(batch);
+    }
+    let mut guard = self.inner.lock();
+    self.wait_until_not_empty(&mut guard, None);
+    while batch.len() < max {
+      match guard.pop_front()? {
+        Some(val) => batch.push(val),
+        None => break,
+      }
+    }
+    Ok(batch)
+  }
+
+  /// Waits for commits until the deque is not empty, or until `deadline` passes.
+  fn wait_until_not_empty(&self, guard: &mut MutexGuard<'_, MmapVecDeque<T>>, deadline: Option<Instant>) {
+    while guard.is_empty() {
+      match deadline {
+        Some(deadline) => {
+          if self.shared.committed.wait_until(guard, deadline).timed_out() {
+            break;
+          }
+        }
+        None => self.shared.committed.wait(guard),
+      }
+    }
+  }
+}
+
+/// Exclusive access to the deque of a `SharedDeque`, returned by `SharedDeque::lock`.
+pub struct SharedDequeGuard<'a, T: Copy> {
+  guard: MutexGuard<'a, MmapVecDeque<T>>,
+}
+
+impl<'a, T: Copy> Deref for SharedDequeGuard<'a, T> {
+  type Target = MmapVecDeque<T>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.guard
+  }
+}
+
+impl<'a, T: Copy> DerefMut for SharedDequeGuard<'a, T> {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.guard
+  }
+}
@@ -0,0 +1,37 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use tempfile::TempDir;
+
+/// Test in-place modification through references and `set`, and that it is persisted by commit.
+#[test]
+fn test_borrowing_accessors() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  dq.extend(0..10);
+  dq.commit()?;
+
+  assert_eq!(dq.get_ref(3), Some(&3));
+  assert_eq!(dq.get_ref(10), None);
+  assert!(dq.get_mut(10)?.is_none());
+  assert!(matches!(dq.set(10, 0), Err(MmapVecDequeError::IndexOutOfRange)));
+
+  *dq.get_mut(5)?.unwrap() += 100;
+  *dq.front_mut()?.unwrap() = 42;
+  *dq.back_mut()?.unwrap() *= 2;
+  dq.set(7, 700)?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![42, 1, 2, 3, 4, 105, 6, 700, 8, 18]);
+
+  // Changes through references are journaled like any other overwrite.
+  dq.rollback()?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+
+  *dq.get_mut(2)?.unwrap() = 200;
+  dq.commit()?;
+  drop(dq);
+
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.get_ref(2), Some(&200));
+  dq.clear()?;
+  assert!(dq.front_mut()?.is_none());
+  assert!(dq.back_mut()?.is_none());
+  Ok(())
+}
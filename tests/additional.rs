@@ -1,19 +1,23 @@
 use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
 use tempfile::TempDir;
 
+mod common;
+
+use common::chunk_files;
+
 /// Test that attempting to reopen a deque with a different type results in an error.
 #[test]
 fn test_type_mismatch() -> Result<(), MmapVecDequeError> {
   let tmp = TempDir::new()?;
   let path = tmp.path();
 
-  let mut dq_u64 = MmapVecDeque::<u64>::open_or_create(path, None)?;
+  let mut dq_u64 = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
   dq_u64.push_back(42)?;
   dq_u64.commit()?;
   drop(dq_u64);
 
   // Attempt to open the same storage as a different type
-  let result = MmapVecDeque::<u32>::open_or_create(path, None);
+  let result = unsafe { MmapVecDeque::<u32>::open_or_create(path, None) };
   assert!(result.is_err(), "Expected error due to type mismatch");
   Ok(())
 }
@@ -25,7 +29,7 @@ fn test_persistence_across_open() -> Result<(), MmapVecDequeError> {
   let path = tmp.path();
 
   {
-    let mut dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     dq.push_back(100)?;
     dq.push_back(200)?;
     dq.push_front(50)?;
@@ -33,7 +37,7 @@ fn test_persistence_across_open() -> Result<(), MmapVecDequeError> {
   }
 
   // Reopen and check data
-  let dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
   assert_eq!(dq.len(), 3);
   assert_eq!(dq.front(), Some(50));
   assert_eq!(dq.back(), Some(200));
@@ -48,7 +52,7 @@ fn test_zero_sized_type_fails() {
   let path = tmp.path();
 
   // Zero-sized type, e.g., ()
-  let result = MmapVecDeque::<()>::open_or_create(path, None);
+  let result = unsafe { MmapVecDeque::<()>::open_or_create(path, None) };
   assert!(result.is_err(), "Zero-sized type should not be supported");
 }
 
@@ -57,7 +61,7 @@ fn test_zero_sized_type_fails() {
 fn test_mixed_operations() -> Result<(), MmapVecDequeError> {
   let tmp = TempDir::new()?;
   let path = tmp.path();
-  let mut dq = MmapVecDeque::<i32>::open_or_create(path, Some(50))?;
+  let mut dq = unsafe { MmapVecDeque::<i32>::open_or_create(path, Some(50)) }?;
 
   // Push front and back alternately
   for i in 0..50 {
@@ -99,7 +103,7 @@ fn test_mixed_operations() -> Result<(), MmapVecDequeError> {
 fn test_clear_then_reuse() -> Result<(), MmapVecDequeError> {
   let tmp = TempDir::new()?;
   let path = tmp.path();
-  let mut dq = MmapVecDeque::<u8>::open_or_create(path, None)?;
+  let mut dq = unsafe { MmapVecDeque::<u8>::open_or_create(path, None) }?;
 
   dq.push_back(10)?;
   dq.push_back(20)?;
@@ -127,7 +131,7 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   // First cycle: create and populate
   {
-    let mut dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     // Insert values 0..50
     for i in 0..50 {
       dq.push_back(i)?;
@@ -137,7 +141,7 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   // Reopen and check
   {
-    let dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     assert_eq!(dq.len(), 50);
     let collected: Vec<u64> = dq.iter().collect();
     assert_eq!(collected, (0..50).collect::<Vec<_>>());
@@ -145,7 +149,7 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   // Second cycle: modify the existing deque
   {
-    let mut dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     // Pop 10 from front
     for _ in 0..10 {
       dq.pop_front()?;
@@ -156,7 +160,7 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   // Reopen and verify changes
   {
-    let dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     assert_eq!(dq.len(), 40);
     let collected: Vec<u64> = dq.iter().collect();
     assert_eq!(collected, (10..50).collect::<Vec<_>>());
@@ -164,7 +168,7 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   // Third cycle: add more at the front
   {
-    let mut dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     // Push_front values 100..110
     for i in 100..110 {
       dq.push_front(i)?;
@@ -174,7 +178,7 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   // Reopen and verify again
   {
-    let dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     assert_eq!(dq.len(), 50);
     let collected: Vec<u64> = dq.iter().collect();
 
@@ -186,14 +190,14 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   // Fourth cycle: clear and reuse
   {
-    let mut dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     dq.clear()?;
     dq.commit()?;
   }
 
   // Reopen and ensure empty
   {
-    let mut dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     assert_eq!(dq.len(), 0);
 
     // Add some different numbers
@@ -205,7 +209,7 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   // Final check
   {
-    let dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+    let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
     assert_eq!(dq.len(), 20);
     let collected: Vec<u64> = dq.iter().collect();
     assert_eq!(collected, (1000..1020).collect::<Vec<_>>());
@@ -213,3 +217,54 @@ fn test_multiple_reopen_cycles() -> Result<(), MmapVecDequeError> {
 
   Ok(())
 }
+
+/// Test that a FIFO workload doesn't leak chunk files, and that stale ones are removed on open.
+#[test]
+fn test_chunk_files_are_reclaimed() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+
+  {
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(4)) }?;
+    for i in 0..1000 {
+      dq.push_back(i)?;
+      if i >= 10 {
+        dq.pop_front()?;
+      }
+      dq.commit()?;
+    }
+    assert_eq!(dq.len(), 10);
+    assert!(chunk_files(path).len() <= 4);
+
+    // Pending chunks are removed again when the changes are rolled back.
+    for i in 0..100 {
+      dq.push_back(i)?;
+    }
+    dq.rollback()?;
+    assert!(chunk_files(path).len() <= 4);
+  }
+
+  // A chunk left behind by a crashed process is cleaned up on open.
+  std::fs::write(path.join("chunk_3.bin"), [0u8; 32])?;
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(4)) }?;
+  assert!(!path.join("chunk_3.bin").exists());
+  let collected: Vec<u64> = dq.iter().collect();
+  assert_eq!(collected, (990..1000).collect::<Vec<_>>());
+
+  Ok(())
+}
+
+/// Test that a deque directory can only be opened by one writer at a time.
+#[test]
+fn test_directory_is_locked_while_open() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
+  let result = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) };
+  assert!(matches!(result, Err(MmapVecDequeError::Locked { .. })));
+
+  drop(dq);
+  unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
+  Ok(())
+}
@@ -0,0 +1,82 @@
+#![cfg(feature = "tokio")]
+
+use futures_core::Stream;
+use mmap_vecdeque::{AsyncMmapVecDeque, MmapVecDequeError};
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::time::Duration;
+use tempfile::TempDir;
+
+async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+  poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+/// Test that async pops wait for a producer task to commit new elements.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_pop_front_wait() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let dq = unsafe { AsyncMmapVecDeque::<u64>::open_or_create(tmp.path(), Some(8)) }.await?;
+
+  let consumer = {
+    let dq = dq.clone();
+    tokio::spawn(async move {
+      let mut received = Vec::new();
+      while received.len() < 50 {
+        received.push(dq.pop_front_wait().await?);
+      }
+      dq.commit().await?;
+      Ok::<_, MmapVecDequeError>(received)
+    })
+  };
+
+  for batch in 0..5 {
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    {
+      let mut guard = dq.lock().await;
+      for i in 0..10 {
+        guard.push_back(batch * 10 + i)?;
+      }
+    }
+    dq.commit().await?;
+  }
+
+  assert_eq!(consumer.await.unwrap()?, (0..50).collect::<Vec<_>>());
+  assert!(dq.lock().await.is_empty());
+  Ok(())
+}
+
+/// Test that the stream yields committed elements only, in order, and none from before it started.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_committed_stream() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let dq = unsafe { AsyncMmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }.await?;
+  dq.lock().await.push_back(1000)?;
+  dq.commit().await?;
+
+  let mut stream = dq.stream().await?;
+  let tail = tokio::spawn(async move {
+    let mut seen = Vec::new();
+    while seen.len() < 30 {
+      seen.push(next(&mut stream).await.expect("stream ended"));
+    }
+    seen
+  });
+
+  for batch in 0..3 {
+    {
+      let mut guard = dq.lock().await;
+      for i in 0..10 {
+        guard.push_back(batch * 10 + i)?;
+      }
+      // Pending elements are not yielded until they are committed.
+      guard.push_back(2000)?;
+      guard.pop_back()?;
+    }
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    dq.commit().await?;
+  }
+
+  let seen = tokio::time::timeout(Duration::from_secs(30), tail).await.expect("timed out").unwrap();
+  assert_eq!(seen, (0..30).collect::<Vec<_>>());
+  Ok(())
+}
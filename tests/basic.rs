@@ -5,7 +5,7 @@ use tempfile::TempDir;
 fn test_basic_ops() -> Result<(), MmapVecDequeError> {
   let tmp = TempDir::new()?;
   let path = tmp.path();
-  let mut dq = MmapVecDeque::<u64>::open_or_create(path, None)?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, None) }?;
 
   // Initially empty
   assert!(dq.is_empty());
@@ -52,7 +52,7 @@ fn test_basic_ops() -> Result<(), MmapVecDequeError> {
 fn test_iteration() -> Result<(), MmapVecDequeError> {
   let tmp = TempDir::new()?;
   let path = tmp.path();
-  let mut dq = MmapVecDeque::<u32>::open_or_create(path, None)?;
+  let mut dq = unsafe { MmapVecDeque::<u32>::open_or_create(path, None) }?;
 
   // Insert 100 values: 0..99
   for i in 0..100 {
@@ -81,7 +81,7 @@ fn test_iteration() -> Result<(), MmapVecDequeError> {
 fn test_large_insertions() -> Result<(), MmapVecDequeError> {
   let tmp = TempDir::new()?;
   let path = tmp.path();
-  let mut dq = MmapVecDeque::<u64>::open_or_create(path, Some(10000))?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(10000)) }?;
 
   // Insert 100,000 items
   for i in 0..100_000 {
@@ -150,7 +150,7 @@ fn test_large_insertions() -> Result<(), MmapVecDequeError> {
 fn test_push_front_many() -> Result<(), MmapVecDequeError> {
   let tmp = TempDir::new()?;
   let path = tmp.path();
-  let mut dq = MmapVecDeque::<i64>::open_or_create(path, None)?;
+  let mut dq = unsafe { MmapVecDeque::<i64>::open_or_create(path, None) }?;
 
   // Push a range of negative numbers at the front
   for i in 0..100 {
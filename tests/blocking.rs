@@ -0,0 +1,66 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError, SharedDeque};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Test a consumer thread blocking on pops while a producer commits new elements.
+#[test]
+fn test_blocking_pop_waits_for_commit() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let dq = SharedDeque::new(unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(8)) }?);
+
+  let consumer = {
+    let dq = dq.clone();
+    thread::spawn(move || -> Result<Vec<u64>, MmapVecDequeError> {
+      let mut received = Vec::new();
+      while received.len() < 100 {
+        if received.len() % 2 == 0 {
+          received.push(dq.pop_front_blocking()?);
+        } else {
+          received.extend(dq.pop_front_batch_blocking(7)?);
+        }
+        dq.commit()?;
+      }
+      Ok(received)
+    })
+  };
+
+  for i in 0..100 {
+    let mut guard = dq.lock();
+    guard.push_back(i)?;
+    guard.commit()?;
+    drop(guard);
+    if i % 10 == 0 {
+      thread::sleep(Duration::from_millis(5));
+    }
+  }
+
+  let received = consumer.join().unwrap()?;
+  assert_eq!(received, (0..100).collect::<Vec<_>>());
+  assert!(dq.lock().is_empty());
+  Ok(())
+}
+
+/// Test that a timed pop gives up on an empty deque and returns early once data is committed.
+#[test]
+fn test_pop_timeout() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let dq = SharedDeque::new(unsafe { MmapVecDeque::<u32>::open_or_create(tmp.path(), None) }?);
+
+  let empty = dq.pop_front_timeout(Duration::from_millis(20))?;
+  assert_eq!(empty, None);
+
+  let producer = {
+    let dq = dq.clone();
+    thread::spawn(move || -> Result<(), MmapVecDequeError> {
+      thread::sleep(Duration::from_millis(20));
+      dq.lock().push_back(7)?;
+      dq.commit()
+    })
+  };
+  let value = dq.pop_front_timeout(Duration::from_secs(10))?;
+  assert_eq!(value, Some(7));
+  producer.join().unwrap()?;
+
+  Ok(())
+}
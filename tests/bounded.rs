@@ -0,0 +1,125 @@
+use mmap_vecdeque::{DequeOptions, MmapVecDeque, MmapVecDequeError, Overflow};
+use tempfile::TempDir;
+
+mod common;
+
+use common::chunk_files;
+
+/// Test that an evicting ring buffer keeps the last elements in a constant set of chunk files.
+#[test]
+fn test_evicting_ring_buffer() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq: MmapVecDeque<u64> = unsafe { DequeOptions::new().chunk_size(4).bounded(10, Overflow::Evict).open(tmp.path()) }?;
+
+  for i in 0..1000 {
+    dq.push_back(i)?;
+    if i % 7 == 0 {
+      dq.commit()?;
+    }
+  }
+  dq.commit()?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (990..1000).collect::<Vec<_>>());
+  assert_eq!(chunk_files(tmp.path()).len(), 4);
+
+  dq.extend_back_from_slice(&(2000..2025).collect::<Vec<_>>())?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (2015..2025).collect::<Vec<_>>());
+  dq.push_front(7)?;
+  assert_eq!(dq.front(), Some(7));
+  assert_eq!(dq.back(), Some(2023));
+  dq.insert(3, 8)?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![2015, 2016, 8, 2017, 2018, 2019, 2020, 2021, 2022, 2023]);
+  dq.commit()?;
+  drop(dq);
+
+  // The bound is stored with the deque.
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  dq.extend(0..5);
+  assert_eq!(dq.len(), 10);
+  assert_eq!(dq.front(), Some(2019));
+  assert_eq!(chunk_files(tmp.path()).len(), 4);
+  Ok(())
+}
+
+/// Test the failing overflow policy and that the capacity can't change once created.
+#[test]
+fn test_full_ring_buffer() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq: MmapVecDeque<u32> = unsafe { DequeOptions::new().chunk_size(4).bounded(5, Overflow::Fail).open(tmp.path()) }?;
+  dq.extend_back_from_slice(&[1, 2, 3, 4])?;
+  assert!(matches!(dq.extend_front_from_slice(&[5, 6]), Err(MmapVecDequeError::Full { capacity: 5 })));
+  dq.push_front(0)?;
+  assert!(matches!(dq.push_back(9), Err(MmapVecDequeError::Full { capacity: 5 })));
+  assert!(matches!(dq.insert(2, 9), Err(MmapVecDequeError::Full { capacity: 5 })));
+  dq.pop_front()?;
+  dq.push_back(5)?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+  dq.commit()?;
+  drop(dq);
+
+  let result = unsafe { DequeOptions::new().chunk_size(4).bounded(6, Overflow::Fail).open::<u32>(tmp.path()) };
+  assert!(matches!(result, Err(MmapVecDequeError::CapacityMismatch { stored: Some(5), requested: Some(6) })));
+  Ok(())
+}
+
+/// Test that wrapping around the ring over committed elements is undone by rollback and
+/// invisible to readers until commit.
+#[test]
+fn test_ring_wraparound_rollback() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq: MmapVecDeque<u64> = unsafe { DequeOptions::new().chunk_size(4).bounded(8, Overflow::Evict).open(tmp.path()) }?;
+  dq.extend(0..8);
+  dq.commit()?;
+  let reader = dq.reader()?;
+
+  // Twelve more pushes reuse every chunk file, including the ones holding committed elements.
+  dq.extend(100..112);
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (104..112).collect::<Vec<_>>());
+  assert_eq!(reader.iter().collect::<Vec<_>>(), (0..8).collect::<Vec<_>>());
+  dq.rollback()?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (0..8).collect::<Vec<_>>());
+  dq.verify()?;
+  drop(reader); // Readers keep the directory locked.
+
+  dq.extend(100..112);
+  drop(dq); // Crash before commit; reopening replays the journal.
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (0..8).collect::<Vec<_>>());
+  Ok(())
+}
+
+/// Test that inserting at the front of a full evicting deque leaves it unchanged.
+#[test]
+fn test_insert_front_when_full() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq: MmapVecDeque<u64> = unsafe { DequeOptions::new().chunk_size(2).bounded(3, Overflow::Evict).open(tmp.path()) }?;
+  dq.extend(0..3);
+  dq.commit()?;
+  dq.insert(0, 99)?;
+  assert_eq!(dq.len(), 3);
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+  dq.insert(1, 99)?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![99, 1, 2]);
+  Ok(())
+}
+
+/// Test that wrapping around the ring several times within one transaction reuses the chunk
+/// files, and that the deque still verifies before and after the commit.
+#[test]
+fn test_ring_wraparound_verify() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq: MmapVecDeque<u64> = unsafe { DequeOptions::new().chunk_size(2).bounded(5, Overflow::Evict).open(tmp.path()) }?;
+  dq.extend(0..5);
+  dq.commit()?;
+  for i in 5..50 {
+    dq.push_back(i)?;
+    dq.verify()?;
+  }
+  assert_eq!(chunk_files(tmp.path()).len(), 4);
+  dq.commit()?;
+  dq.verify()?;
+  drop(dq);
+
+  let dq: MmapVecDeque<u64> = unsafe { DequeOptions::new().chunk_size(2).bounded(5, Overflow::Evict).open(tmp.path()) }?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (45..50).collect::<Vec<_>>());
+  Ok(())
+}
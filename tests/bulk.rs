@@ -0,0 +1,51 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use tempfile::TempDir;
+
+/// Test bulk pushes and pops spanning several chunks at both ends.
+#[test]
+fn test_slice_push_and_pop() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(8)) }?;
+
+  let back: Vec<u64> = (100..150).collect();
+  let front: Vec<u64> = (0..21).collect();
+  dq.extend_back_from_slice(&back)?;
+  dq.extend_front_from_slice(&front)?;
+  dq.extend_back_from_slice(&[])?;
+  dq.commit()?;
+  let expected: Vec<u64> = front.iter().chain(&back).copied().collect();
+  assert_eq!(dq.iter().collect::<Vec<_>>(), expected);
+
+  let mut buf = [0u64; 10];
+  assert_eq!(dq.pop_front_into(&mut buf)?, 10);
+  assert_eq!(buf.to_vec(), (0..10).collect::<Vec<_>>());
+  assert_eq!(dq.pop_back_into(&mut buf)?, 10);
+  assert_eq!(buf.to_vec(), (140..150).collect::<Vec<_>>());
+  assert_eq!(dq.len(), expected.len() - 20);
+
+  let mut rest = vec![0u64; 100];
+  let n = dq.pop_front_into(&mut rest)?;
+  assert_eq!(&rest[..n], &expected[10..expected.len() - 10]);
+  assert_eq!(dq.pop_back_into(&mut buf)?, 0);
+
+  // Pops stay pending until commit.
+  dq.rollback()?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), expected);
+  Ok(())
+}
+
+/// Test that `Extend` appends in order and the result survives a reopen.
+#[test]
+fn test_extend() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u32>::open_or_create(tmp.path(), Some(16)) }?;
+  dq.push_back(7)?;
+  dq.extend(0..1000);
+  dq.extend(std::iter::empty());
+  dq.commit()?;
+  drop(dq);
+
+  let dq = unsafe { MmapVecDeque::<u32>::open_or_create(tmp.path(), Some(16)) }?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), std::iter::once(7).chain(0..1000).collect::<Vec<_>>());
+  Ok(())
+}
@@ -0,0 +1,43 @@
+use mmap_vecdeque::{MmapBytesDeque, MmapVecDequeError};
+use tempfile::TempDir;
+
+/// Test pushing and popping byte records of varying size, including empty ones and one larger than
+/// a segment, with commit, rollback and reopen.
+#[test]
+fn test_bytes_deque() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = MmapBytesDeque::open_or_create(tmp.path(), Some(4))?;
+  let big = vec![7u8; 3 << 20];
+  dq.push_back(b"hello")?;
+  dq.push_back(b"")?;
+  dq.push_back(&big)?;
+  dq.push_front(b"first")?;
+  dq.commit()?;
+
+  assert_eq!(dq.len(), 4);
+  assert_eq!(dq.front()?, Some(&b"first"[..]));
+  assert_eq!(dq.get(2)?, Some(&b""[..]));
+  assert_eq!(dq.back()?.map(|b| b.len()), Some(big.len()));
+  assert_eq!(dq.iter().rev().skip(1).collect::<Result<Vec<_>, _>>()?, vec![&b""[..], b"hello", b"first"]);
+
+  assert_eq!(dq.pop_back()?, Some(&big[..]));
+  assert_eq!(dq.pop_front()?, Some(&b"first"[..]));
+  dq.rollback()?;
+  assert_eq!(dq.len(), 4);
+
+  dq.pop_front()?;
+  dq.push_back(b"uncommitted")?;
+  dq.commit()?;
+  dq.push_back(b"lost")?;
+  drop(dq);
+
+  // Appending after a reopen overwrites the uncommitted record, never a committed one.
+  let mut dq = MmapBytesDeque::open_or_create(tmp.path(), Some(4))?;
+  dq.push_back(b"after reopen")?;
+  dq.commit()?;
+  let records = dq.iter().collect::<Result<Vec<_>, _>>()?;
+  assert_eq!(records[0], b"hello");
+  assert_eq!(records[2], &big[..]);
+  assert_eq!(&records[3..], [&b"uncommitted"[..], b"after reopen"]);
+  Ok(())
+}
@@ -0,0 +1,25 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Paths of the files in `dir` whose names start with `prefix`, sorted.
+pub fn files_starting_with(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+  let mut files: Vec<PathBuf> = fs::read_dir(dir)
+    .unwrap()
+    .map(|e| e.unwrap().path())
+    .filter(|p| p.file_name().unwrap().to_string_lossy().starts_with(prefix))
+    .collect();
+  files.sort();
+  files
+}
+
+/// Chunk files of the deque in `dir`, of every generation.
+pub fn chunk_files(dir: &Path) -> Vec<PathBuf> {
+  files_starting_with(dir, "chunk_")
+}
+
+/// Segment files of the byte or serde deque in `dir`.
+pub fn segment_files(dir: &Path) -> Vec<PathBuf> {
+  files_starting_with(dir, "segment_")
+}
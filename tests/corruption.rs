@@ -0,0 +1,105 @@
+use mmap_vecdeque::{DequeOptions, MmapVecDeque, MmapVecDequeError};
+use std::fs::{self, OpenOptions};
+use tempfile::TempDir;
+
+mod common;
+
+use common::chunk_files;
+
+fn populate(path: &std::path::Path) -> Result<(), MmapVecDequeError> {
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(16)) }?;
+  for i in 0..40 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+  Ok(())
+}
+
+/// Test that a flipped bit in a committed element is reported on open: in the last chunk by
+/// default, and in a middle chunk with `verify_on_open`.
+#[test]
+fn test_corrupted_chunk_detected_on_open() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+  populate(path)?;
+
+  let chunk = chunk_files(path)[1].clone();
+  let mut data = fs::read(&chunk)?;
+  data[8] ^= 0x01;
+  fs::write(&chunk, data)?;
+
+  let mut options = DequeOptions::new();
+  options.chunk_size(16);
+  let dq = unsafe { options.open::<u64>(path) }?;
+  assert!(matches!(dq.verify(), Err(MmapVecDequeError::Corruption { chunk: Some(_), .. })));
+  drop(dq);
+  assert!(matches!(
+    unsafe { options.clone().verify_on_open().open::<u64>(path) },
+    Err(MmapVecDequeError::Corruption { chunk: Some(_), .. })
+  ));
+  assert!(matches!(
+    unsafe { options.clone().verify_on_open().open_read_only::<u64>(path) },
+    Err(MmapVecDequeError::Corruption { chunk: Some(_), .. })
+  ));
+
+  let chunk = chunk_files(path)[2].clone();
+  let mut data = fs::read(&chunk)?;
+  data[8] ^= 0x01;
+  fs::write(&chunk, data)?;
+  match unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(16)) } {
+    Err(MmapVecDequeError::Corruption { chunk: Some(_), .. }) => Ok(()),
+    other => panic!("expected chunk corruption, got {:?}", other.map(|_| ())),
+  }
+}
+
+/// Test that a truncated chunk file and a damaged metadata file are reported.
+#[test]
+fn test_truncated_files_detected_on_open() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+  populate(path)?;
+
+  let chunk = chunk_files(path)[0].clone();
+  OpenOptions::new().write(true).open(&chunk)?.set_len(10)?;
+  assert!(matches!(
+    unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(16)) },
+    Err(MmapVecDequeError::Corruption { chunk: Some(_), .. })
+  ));
+
+  let metadata = path.join("metadata.bin");
+  let len = fs::metadata(&metadata)?.len();
+  OpenOptions::new().write(true).open(&metadata)?.set_len(len - 1)?;
+  assert!(matches!(
+    unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(16)) },
+    Err(MmapVecDequeError::Corruption { chunk: None, .. })
+  ));
+
+  Ok(())
+}
+
+/// Test that verify() catches corruption of an open deque but ignores uncommitted writes.
+#[test]
+fn test_verify_open_deque() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(16)) }?;
+  for i in 0..40 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+  dq.verify()?;
+
+  dq.pop_back()?;
+  dq.push_back(1234)?;
+  dq.verify()?;
+  dq.commit()?;
+  dq.verify()?;
+
+  let chunk = chunk_files(path)[0].clone();
+  let mut data = fs::read(&chunk)?;
+  data[0] ^= 0xff;
+  fs::write(&chunk, data)?;
+  assert!(matches!(dq.verify(), Err(MmapVecDequeError::Corruption { chunk: Some(_), .. })));
+
+  Ok(())
+}
@@ -0,0 +1,64 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use std::fs;
+use tempfile::TempDir;
+
+/// Test that committed slots overwritten without a commit are restored on reopen.
+#[test]
+fn test_uncommitted_overwrite_is_undone_on_open() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+
+  {
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(4)) }?;
+    for i in 0..10 {
+      dq.push_back(i)?;
+    }
+    dq.commit()?;
+
+    // Overwrite committed slots in place, then "crash" without committing.
+    dq.pop_back()?;
+    dq.push_back(999)?;
+    for val in dq.iter_mut() {
+      *val += 1000;
+    }
+    assert!(path.join("journal.bin").exists());
+  }
+
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(4)) }?;
+  assert!(!path.join("journal.bin").exists());
+  let collected: Vec<u64> = dq.iter().collect();
+  assert_eq!(collected, (0..10).collect::<Vec<_>>());
+
+  Ok(())
+}
+
+/// Test that a commit interrupted after writing metadata but before removing the journal
+/// reverts to the previous commit as a whole.
+#[test]
+fn test_interrupted_commit_reverts_metadata() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+
+  {
+    let mut dq = unsafe { MmapVecDeque::<u32>::open_or_create(path, Some(4)) }?;
+    for i in 0..6 {
+      dq.push_back(i)?;
+    }
+    dq.commit()?;
+
+    dq.pop_back()?;
+    dq.push_back(100)?;
+    dq.push_back(101)?;
+    let journal = fs::read(path.join("journal.bin"))?;
+    dq.commit()?;
+
+    // Put the journal back as if the process died right before deleting it.
+    fs::write(path.join("journal.bin"), journal)?;
+  }
+
+  let dq = unsafe { MmapVecDeque::<u32>::open_or_create(path, Some(4)) }?;
+  let collected: Vec<u32> = dq.iter().collect();
+  assert_eq!(collected, (0..6).collect::<Vec<_>>());
+
+  Ok(())
+}
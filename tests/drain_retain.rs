@@ -0,0 +1,110 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use std::collections::VecDeque;
+use tempfile::TempDir;
+
+fn contents(dq: &MmapVecDeque<u64>) -> Vec<u64> {
+  dq.iter().collect()
+}
+
+/// Test truncate, truncate_front and drain against `std::collections::VecDeque`, across chunks.
+#[test]
+fn test_truncate_and_drain() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  let mut expected: VecDeque<u64> = (0..50).collect();
+  for i in 0..50 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+
+  dq.truncate(45);
+  expected.truncate(45);
+  dq.truncate(100);
+  dq.truncate_front(40);
+  expected.drain(..5);
+  assert_eq!(contents(&dq), expected.iter().copied().collect::<Vec<_>>());
+
+  // Near the front, near the back, and all of it.
+  let drained = dq.drain(2..7)?.collect::<Vec<_>>();
+  assert_eq!(drained, expected.drain(2..7).collect::<Vec<_>>());
+  let drained = dq.drain(25..=30)?.rev().collect::<Vec<_>>();
+  assert_eq!(drained, expected.drain(25..=30).rev().collect::<Vec<_>>());
+  assert_eq!(contents(&dq), expected.iter().copied().collect::<Vec<_>>());
+  assert!(matches!(dq.drain(10..100), Err(MmapVecDequeError::IndexOutOfRange)));
+
+  dq.commit()?;
+  drop(dq);
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(contents(&dq), expected.iter().copied().collect::<Vec<_>>());
+  assert_eq!(dq.drain(..)?.len(), expected.len());
+  assert!(dq.is_empty());
+  dq.rollback()?;
+  assert_eq!(contents(&dq), expected.iter().copied().collect::<Vec<_>>());
+  Ok(())
+}
+
+/// Test retain and retain_mut, including that they stay pending until commit.
+#[test]
+fn test_retain() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  for i in 0..30 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+
+  let mut visited = Vec::new();
+  dq.retain(|val| {
+    visited.push(*val);
+    val % 3 != 0
+  })?;
+  assert_eq!(visited, (0..30).collect::<Vec<_>>());
+  assert_eq!(contents(&dq), (0..30).filter(|v| v % 3 != 0).collect::<Vec<_>>());
+  dq.rollback()?;
+  assert_eq!(contents(&dq), (0..30).collect::<Vec<_>>());
+
+  dq.retain_mut(|val| {
+    *val *= 10;
+    *val >= 100
+  })?;
+  dq.commit()?;
+  drop(dq);
+
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(contents(&dq), (10..30).map(|v| v * 10).collect::<Vec<_>>());
+  Ok(())
+}
+
+/// Test that a drain dropped early still removes its whole range, and that a panicking
+/// retain_mut closure leaves every element exactly once.
+#[test]
+fn test_drain_dropped_and_retain_panic() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  let mut expected: VecDeque<u64> = (0..30).collect();
+  for i in 0..30 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+
+  let mut drain = dq.drain(3..20)?;
+  assert_eq!(drain.next(), Some(3));
+  assert_eq!(drain.next_back(), Some(19));
+  drop(drain);
+  expected.drain(3..20);
+  assert_eq!(contents(&dq), expected.iter().copied().collect::<Vec<_>>());
+
+  let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    dq.retain_mut(|val| {
+      assert!(*val < 25, "stop");
+      *val % 2 == 0
+    })
+  }));
+  assert!(result.is_err());
+  let retained = expected.iter().copied().filter(|v| *v >= 25 || v % 2 == 0).collect::<Vec<_>>();
+  assert_eq!(contents(&dq), retained);
+  assert_eq!(dq.len(), retained.len());
+  dq.rollback()?;
+  assert_eq!(contents(&dq), (0..30).collect::<Vec<_>>());
+  Ok(())
+}
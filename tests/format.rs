@@ -0,0 +1,67 @@
+use mmap_vecdeque::{DequeOptions, MmapVecDeque, MmapVecDequeError};
+use serde::Serialize;
+use std::fs;
+use tempfile::TempDir;
+
+/// The headerless metadata written by releases up to 0.2.
+#[derive(Serialize)]
+struct LegacyMetadata {
+  type_name: String,
+  element_size: usize,
+  chunk_size: usize,
+  start: u64,
+  end: u64,
+}
+
+/// Test that a deque in the legacy format is read in place and upgraded when opened for writing.
+#[test]
+fn test_upgrade_legacy_format() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let start = 1u64 << 32;
+  let values: Vec<u64> = (0..10).collect();
+  for (chunk, values) in values.chunks(4).enumerate() {
+    let mut bytes: Vec<u8> = values.iter().flat_map(|v| v.to_ne_bytes()).collect();
+    bytes.resize(4 * 8, 0);
+    fs::write(tmp.path().join(format!("chunk_{}.bin", (start / 4) as usize + chunk)), bytes)?;
+  }
+  let legacy = LegacyMetadata { type_name: "u64".to_string(), element_size: 8, chunk_size: 4, start, end: start + 10 };
+  fs::write(tmp.path().join("metadata.bin"), postcard::to_stdvec(&legacy)?)?;
+
+  let reader = unsafe { DequeOptions::new().open_read_only::<u64>(tmp.path()) }?;
+  assert_eq!(reader.iter().collect::<Vec<_>>(), values);
+  drop(reader);
+  assert!(!fs::read(tmp.path().join("metadata.bin"))?.starts_with(b"MVDQ"));
+
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  assert!(fs::read(tmp.path().join("metadata.bin"))?.starts_with(b"MVDQ"));
+  assert_eq!(dq.iter().collect::<Vec<_>>(), values);
+  dq.push_back(10)?;
+  dq.commit()?;
+  drop(dq);
+
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.len(), 11);
+  Ok(())
+}
+
+/// Test that new deques get the current header and that newer formats are refused.
+#[test]
+fn test_format_version() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  drop(unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), None) }?);
+  let path = tmp.path().join("metadata.bin");
+  let mut data = fs::read(&path)?;
+  assert_eq!(data[..8], [b'M', b'V', b'D', b'Q', 1, 0, 0, 0]);
+
+  data[4..8].copy_from_slice(&99u32.to_le_bytes());
+  fs::write(&path, data)?;
+  assert!(matches!(
+    unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), None) },
+    Err(MmapVecDequeError::UnsupportedFormatVersion { found: 99, supported: 1 })
+  ));
+  assert!(matches!(
+    unsafe { DequeOptions::new().open_read_only::<u64>(tmp.path()) },
+    Err(MmapVecDequeError::UnsupportedFormatVersion { found: 99, supported: 1 })
+  ));
+  Ok(())
+}
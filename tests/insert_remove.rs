@@ -0,0 +1,84 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use std::collections::VecDeque;
+use tempfile::TempDir;
+
+/// Small deterministic generator, so failures are reproducible.
+struct Lcg(u64);
+
+impl Lcg {
+  fn next(&mut self, bound: usize) -> usize {
+    self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    ((self.0 >> 33) % bound as u64) as usize
+  }
+}
+
+/// Test arbitrary-index insertion and removal against `std::collections::VecDeque`, across
+/// chunk boundaries and commits.
+#[test]
+fn test_matches_std_vecdeque() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  let mut expected = VecDeque::new();
+  let mut rng = Lcg(7);
+
+  for step in 0..2000u64 {
+    let len = expected.len();
+    match rng.next(6) {
+      0 | 1 => {
+        let index = rng.next(len + 1);
+        dq.insert(index, step)?;
+        expected.insert(index, step);
+      }
+      2 => {
+        let index = rng.next(len + 1);
+        assert_eq!(dq.remove(index)?, expected.remove(index));
+      }
+      3 => {
+        let index = rng.next(len + 1);
+        assert_eq!(dq.swap_remove_front(index)?, expected.swap_remove_front(index));
+      }
+      4 => {
+        let index = rng.next(len + 1);
+        assert_eq!(dq.swap_remove_back(index)?, expected.swap_remove_back(index));
+      }
+      _ => {
+        dq.push_back(step)?;
+        expected.push_back(step);
+      }
+    }
+    if step % 97 == 0 {
+      dq.commit()?;
+    }
+  }
+  assert_eq!(dq.iter().collect::<Vec<_>>(), expected.iter().copied().collect::<Vec<_>>());
+  dq.commit()?;
+  drop(dq);
+
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), expected.iter().copied().collect::<Vec<_>>());
+  Ok(())
+}
+
+/// Test that shifting committed elements is undone by rollback, and out-of-range inserts fail.
+#[test]
+fn test_insert_remove_rollback() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  for i in 0..20 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+
+  assert!(matches!(dq.insert(21, 0), Err(MmapVecDequeError::IndexOutOfRange)));
+  assert_eq!(dq.remove(20)?, None);
+
+  dq.insert(3, 100)?;
+  dq.insert(15, 101)?;
+  assert_eq!(dq.remove(10)?, Some(9));
+  assert_eq!(dq.swap_remove_back(0)?, Some(0));
+  assert_eq!(dq.front(), Some(19));
+
+  dq.rollback()?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+  Ok(())
+}
@@ -0,0 +1,85 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use tempfile::TempDir;
+
+/// Test that iterators walk both ends across chunks and skip with nth/nth_back.
+#[test]
+fn test_double_ended_iteration() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  for i in 0..10 {
+    dq.push_back(i)?;
+  }
+  for i in 1..=5 {
+    dq.push_front(100 + i)?;
+  }
+  let expected: Vec<u64> = (1..=5).rev().map(|i| 100 + i).chain(0..10).collect();
+
+  assert_eq!(dq.iter().collect::<Vec<_>>(), expected);
+  assert_eq!(dq.iter().rev().collect::<Vec<_>>(), expected.iter().rev().copied().collect::<Vec<_>>());
+
+  let mut iter = dq.iter();
+  assert_eq!(iter.len(), 15);
+  assert_eq!(iter.next(), Some(105));
+  assert_eq!(iter.next_back(), Some(9));
+  assert_eq!(iter.nth(4), Some(0));
+  assert_eq!(iter.nth_back(2), Some(6));
+  assert_eq!(iter.size_hint(), (5, Some(5)));
+  assert_eq!(iter.by_ref().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+  assert_eq!(iter.next(), None);
+  assert_eq!(iter.next_back(), None);
+
+  let mut iter = dq.iter();
+  assert_eq!(iter.nth(100), None);
+  assert_eq!(iter.next(), None);
+
+  for (i, val) in dq.iter_mut().rev().enumerate() {
+    *val = i as u64;
+  }
+  let mut iter = dq.iter_mut();
+  assert_eq!(iter.nth_back(3).copied(), Some(3));
+  assert_eq!(iter.nth(1).copied(), Some(13));
+  assert_eq!(iter.len(), 9);
+  Ok(())
+}
+
+/// Test that taking the first few elements of a long deque only touches the chunks it needs.
+#[test]
+fn test_iter_is_lazy() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u32>::open_or_create(tmp.path(), Some(1024)) }?;
+  dq.extend(0..200_000);
+  assert_eq!(dq.iter().take(3).collect::<Vec<_>>(), vec![0, 1, 2]);
+  assert_eq!(dq.iter().nth_back(0), Some(199_999));
+  assert_eq!(dq.iter().skip(150_000).step_by(10_000).collect::<Vec<_>>(), vec![150_000, 160_000, 170_000, 180_000, 190_000]);
+  Ok(())
+}
+
+/// Test range iteration and per-chunk slices, including partial chunks at both ends.
+#[test]
+fn test_ranges_and_chunk_slices() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  dq.extend(0..14);
+  dq.pop_front()?;
+  dq.commit()?;
+
+  assert_eq!(dq.range(2..6)?.collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+  assert_eq!(dq.range(..=1)?.rev().collect::<Vec<_>>(), vec![2, 1]);
+  assert_eq!(dq.range(13..)?.count(), 0);
+  assert!(matches!(dq.range(5..14), Err(MmapVecDequeError::IndexOutOfRange)));
+
+  let slices = dq.chunks_slices().collect::<Vec<_>>();
+  assert_eq!(slices, vec![&[1, 2, 3][..], &[4, 5, 6, 7][..], &[8, 9, 10, 11][..], &[12, 13][..]]);
+  let mut slices = dq.chunks_slices();
+  assert_eq!(slices.next_back(), Some(&[12, 13][..]));
+  assert_eq!(slices.next(), Some(&[1, 2, 3][..]));
+  assert_eq!(slices.count(), 2);
+
+  for val in dq.range_mut(4..8)? {
+    *val *= 100;
+  }
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 500, 600, 700, 800, 9, 10, 11, 12, 13]);
+  dq.rollback()?;
+  assert_eq!(dq.chunks_slices().flatten().copied().collect::<Vec<_>>(), (1..14).collect::<Vec<_>>());
+  Ok(())
+}
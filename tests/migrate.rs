@@ -0,0 +1,87 @@
+use mmap_vecdeque::{persistent_type, DequeOptions, MmapVecDeque, MmapVecDequeError};
+use std::panic::{self, AssertUnwindSafe};
+use tempfile::TempDir;
+
+mod common;
+
+use common::chunk_files;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+struct Old {
+  id: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+struct New {
+  id: u64,
+  score: u32,
+}
+
+persistent_type!(New, "test.record", 2);
+
+/// Test that migration converts every element, keeps the chunk size, and replaces the old files.
+#[test]
+fn test_migrate() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<Old>::open_or_create(tmp.path(), Some(4)) }?;
+  for id in 0..10 {
+    dq.push_back(Old { id })?;
+  }
+  dq.pop_front()?;
+  dq.commit()?;
+  // Uncommitted changes are not migrated.
+  dq.push_back(Old { id: 99 })?;
+  drop(dq);
+
+  assert!(matches!(unsafe { MmapVecDeque::<New>::open_or_create(tmp.path(), Some(4)) }, Err(MmapVecDequeError::ElementSizeMismatch { .. })));
+  assert!(matches!(unsafe { MmapVecDeque::<New>::migrate(tmp.path(), |old: u64| New { id: old, score: 0 }) }, Err(MmapVecDequeError::ElementSizeMismatch { .. })));
+
+  unsafe { MmapVecDeque::migrate(tmp.path(), |old: Old| New { id: old.id as u64, score: old.id * 10 }) }?;
+  assert!(chunk_files(tmp.path()).iter().all(|path| path.to_string_lossy().contains(".gen1.")));
+  assert!(matches!(unsafe { MmapVecDeque::<Old>::open_or_create(tmp.path(), Some(4)) }, Err(MmapVecDequeError::ElementSizeMismatch { .. })));
+
+  let mut dq = unsafe { MmapVecDeque::<New>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (1..10).map(|id| New { id, score: id as u32 * 10 }).collect::<Vec<_>>());
+  dq.push_back(New { id: 10, score: 100 })?;
+  dq.commit()?;
+  drop(dq);
+
+  // Migration can also move the deque over to a persistent type identity.
+  let mut options = DequeOptions::new();
+  options.persistent::<New>().accept_type_name(std::any::type_name::<New>());
+  unsafe { options.migrate(tmp.path(), |old: New| New { score: old.score + 1, ..old }) }?;
+  let dq = unsafe { MmapVecDeque::<New>::open_persistent(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.back(), Some(New { id: 10, score: 101 }));
+  assert!(chunk_files(tmp.path()).iter().all(|path| path.to_string_lossy().contains(".gen2.")));
+  Ok(())
+}
+
+/// Test that a migration interrupted before its metadata is written leaves the old deque intact.
+#[test]
+fn test_interrupted_migration() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<Old>::open_or_create(tmp.path(), Some(4)) }?;
+  for id in 0..20 {
+    dq.push_back(Old { id })?;
+  }
+  dq.commit()?;
+  drop(dq);
+  let before = chunk_files(tmp.path());
+
+  let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    let convert = |old: Old| {
+      assert!(old.id < 13, "simulated crash");
+      New { id: old.id as u64, score: 0 }
+    };
+    unsafe { MmapVecDeque::migrate(tmp.path(), convert) }
+  }));
+  assert!(result.is_err());
+  assert_ne!(chunk_files(tmp.path()), before);
+
+  let dq = unsafe { MmapVecDeque::<Old>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), (0..20).map(|id| Old { id }).collect::<Vec<_>>());
+  assert_eq!(chunk_files(tmp.path()), before);
+  Ok(())
+}
@@ -0,0 +1,83 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+const CHILD_DIR_VAR: &str = "MMAP_VECDEQUE_NOTIFY_CHILD_DIR";
+
+/// Test that a follower wakes up on commits made through another handle and tails them.
+#[test]
+fn test_follower_waits_for_commit() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  let mut follower = unsafe { MmapVecDeque::<u64>::open_follower(tmp.path()) }?;
+  let seq = follower.commit_seq()?;
+  assert!(follower.is_empty());
+  assert_eq!(follower.wait_for_commit(seq, Duration::from_millis(20))?, None);
+
+  let tail = thread::spawn(move || -> Result<usize, MmapVecDequeError> {
+    let mut seq = seq;
+    let mut refreshes = 0;
+    while follower.len() < 100 {
+      seq = follower.wait_for_commit(seq, Duration::from_secs(30))?.expect("timed out waiting for a commit");
+      assert!(follower.refresh()?);
+      refreshes += 1;
+      // Only whole commits are ever visible.
+      assert_eq!(follower.len() % 10, 0);
+      assert_eq!(follower.iter().collect::<Vec<_>>(), (0..follower.len() as u64).collect::<Vec<_>>());
+    }
+    Ok(refreshes)
+  });
+
+  for batch in 0..10 {
+    for i in 0..10 {
+      dq.push_back(batch * 10 + i)?;
+    }
+    dq.commit()?;
+    thread::sleep(Duration::from_millis(5));
+  }
+
+  assert!(tail.join().unwrap()? >= 1);
+  Ok(())
+}
+
+/// Test that a follower is woken by a commit made in another process.
+#[test]
+fn test_wait_for_commit_across_processes() -> Result<(), MmapVecDequeError> {
+  if let Ok(dir) = std::env::var(CHILD_DIR_VAR) {
+    // Child side: wait for the parent to start waiting, then commit.
+    let follower = unsafe { MmapVecDeque::<u64>::open_follower(Path::new(&dir)) }?;
+    thread::sleep(Duration::from_millis(100));
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(Path::new(&dir), Some(4)) }?;
+    dq.push_back(42)?;
+    dq.commit()?;
+    assert_eq!(follower.commit_seq()?, 2);
+    return Ok(());
+  }
+
+  let tmp = TempDir::new()?;
+  {
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+    dq.push_back(1)?;
+    dq.commit()?;
+  }
+  let mut follower = unsafe { MmapVecDeque::<u64>::open_follower(tmp.path()) }?;
+  let seq = follower.commit_seq()?;
+  assert_eq!(seq, 1);
+
+  let mut child = Command::new(std::env::current_exe()?)
+    .args(["--exact", "test_wait_for_commit_across_processes", "--test-threads=1"])
+    .env(CHILD_DIR_VAR, tmp.path())
+    .spawn()?;
+  let started = Instant::now();
+  assert_eq!(follower.wait_for_commit(seq, Duration::from_secs(30))?, Some(2));
+  assert!(started.elapsed() < Duration::from_secs(30));
+  assert!(child.wait()?.success());
+
+  assert!(follower.refresh()?);
+  assert_eq!(follower.iter().collect::<Vec<_>>(), vec![1, 42]);
+  assert!(!follower.refresh()?);
+  Ok(())
+}
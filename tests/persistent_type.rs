@@ -0,0 +1,70 @@
+use mmap_vecdeque::{persistent_type, DequeOptions, MmapVecDeque, MmapVecDequeError};
+use tempfile::TempDir;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+struct Point {
+  x: i32,
+  y: i32,
+}
+
+persistent_type!(Point, "test.point", 2);
+
+/// Stands in for `Point` in a newer layout version.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+struct PointV3 {
+  x: i32,
+  y: i32,
+}
+
+persistent_type!(PointV3, "test.point", 3);
+
+/// Test that persistent types are identified by their stable name and layout version.
+#[test]
+fn test_persistent_identity() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<Point>::open_persistent(tmp.path(), Some(4)) }?;
+  dq.push_back(Point { x: 1, y: 2 })?;
+  dq.commit()?;
+  drop(dq);
+
+  // Without the trait, the type is identified by `std::any::type_name` as before.
+  assert!(matches!(unsafe { MmapVecDeque::<Point>::open_or_create(tmp.path(), Some(4)) }, Err(MmapVecDequeError::TypeMismatch { .. })));
+  assert!(matches!(
+    unsafe { MmapVecDeque::<PointV3>::open_persistent(tmp.path(), Some(4)) },
+    Err(MmapVecDequeError::LayoutVersionMismatch { stored: 2, requested: 3 })
+  ));
+
+  let reader = unsafe { DequeOptions::new().persistent::<Point>().open_read_only::<Point>(tmp.path()) }?;
+  assert_eq!(reader.front(), Some(Point { x: 1, y: 2 }));
+  drop(reader);
+
+  let dq = unsafe { MmapVecDeque::<Point>::open_persistent(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.len(), 1);
+  Ok(())
+}
+
+/// Test that a deque stored under `std::any::type_name` can be taken over by a persistent type.
+#[test]
+fn test_accept_stored_type_name() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), None) }?;
+  dq.push_back(7)?;
+  dq.commit()?;
+  drop(dq);
+
+  #[derive(Clone, Copy)]
+  struct Counter(#[allow(dead_code)] u64);
+  persistent_type!(Counter, "test.counter");
+
+  assert!(matches!(unsafe { MmapVecDeque::<Counter>::open_persistent(tmp.path(), None) }, Err(MmapVecDequeError::TypeMismatch { .. })));
+  let dq: MmapVecDeque<Counter> = unsafe { DequeOptions::new().persistent::<Counter>().accept_type_name("u64").open(tmp.path()) }?;
+  assert_eq!(dq.len(), 1);
+  drop(dq);
+
+  // The new name was stored, so the override isn't needed anymore and the old name is refused.
+  unsafe { MmapVecDeque::<Counter>::open_persistent(tmp.path(), None) }?;
+  assert!(matches!(unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), None) }, Err(MmapVecDequeError::TypeMismatch { .. })));
+  Ok(())
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "bytemuck")]
+
+use bytemuck::{Pod, Zeroable};
+use mmap_vecdeque::{DequeOptions, MmapVecDeque, MmapVecDequeError};
+use tempfile::TempDir;
+
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+struct Sample {
+  timestamp: u64,
+  value: f32,
+  channel: u32,
+}
+
+/// Test that `Pod` element types open through the safe constructors and round-trip.
+#[test]
+fn test_open_pod() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = MmapVecDeque::<Sample>::open_pod(tmp.path(), Some(4))?;
+  for i in 0..10 {
+    dq.push_back(Sample { timestamp: i, value: i as f32 / 2.0, channel: (i % 3) as u32 })?;
+  }
+  dq.commit()?;
+  drop(dq);
+
+  let dq: MmapVecDeque<Sample> = DequeOptions::new().chunk_size(4).open_pod(tmp.path())?;
+  assert_eq!(dq.len(), 10);
+  assert_eq!(dq.get(7), Some(Sample { timestamp: 7, value: 3.5, channel: 1 }));
+  Ok(())
+}
+
+/// Test that `Pod` element types can be read from a directory through the safe reader constructors.
+#[test]
+fn test_open_readers_pod() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = MmapVecDeque::<Sample>::open_pod(tmp.path(), Some(4))?;
+  for i in 0..6 {
+    dq.push_back(Sample { timestamp: i, value: i as f32, channel: 0 })?;
+  }
+  dq.commit()?;
+
+  let mut follower = MmapVecDeque::<Sample>::open_follower_pod(tmp.path())?;
+  dq.pop_front()?;
+  dq.commit()?;
+  follower.refresh()?;
+  assert_eq!(follower.len(), 5);
+  assert_eq!(DequeOptions::new().open_follower_pod::<Sample>(tmp.path())?.front(), Some(Sample { timestamp: 1, value: 1.0, channel: 0 }));
+  drop(follower);
+  drop(dq);
+
+  let reader = MmapVecDeque::<Sample>::open_read_only_pod(tmp.path())?;
+  assert_eq!(reader.iter().map(|sample| sample.timestamp).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+  let reader = DequeOptions::new().open_read_only_pod::<Sample>(tmp.path())?;
+  assert_eq!(reader.len(), 5);
+  Ok(())
+}
@@ -0,0 +1,135 @@
+use mmap_vecdeque::{DequeOptions, MmapVecDeque, MmapVecDequeError};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+mod common;
+
+use common::chunk_files;
+
+/// The contents of `metadata.bin`, as described in FORMAT.md.
+#[derive(Serialize, Deserialize)]
+struct Metadata {
+  type_name: String,
+  layout_version: u32,
+  element_size: usize,
+  chunk_size: usize,
+  start: u64,
+  end: u64,
+  checksums: Vec<u32>,
+  capacity: Option<u64>,
+  overflow: u32,
+  generation: u64,
+  little_endian: bool,
+  pointer_width: u32,
+  element_align: usize,
+  portable: bool,
+}
+
+fn read_metadata(dir: &Path) -> Result<Metadata, MmapVecDequeError> {
+  let data = fs::read(dir.join("metadata.bin"))?;
+  Ok(postcard::from_bytes(&data[8..data.len() - 4])?)
+}
+
+fn edit_metadata(dir: &Path, f: impl FnOnce(&mut Metadata)) -> Result<(), MmapVecDequeError> {
+  let data = fs::read(dir.join("metadata.bin"))?;
+  let mut meta = read_metadata(dir)?;
+  f(&mut meta);
+  let mut data = data[..8].to_vec();
+  data.extend(postcard::to_stdvec(&meta)?);
+  data.extend(crc32c::crc32c(&data).to_le_bytes());
+  fs::write(dir.join("metadata.bin"), data)?;
+  Ok(())
+}
+
+/// Test that a portable deque stored in the other byte order is read as is by portable readers,
+/// and converted to little-endian when opened for writing.
+#[test]
+fn test_portable_byte_order() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut options = DequeOptions::new();
+  options.chunk_size(4).portable::<u64>();
+  let mut dq: MmapVecDeque<u64> = unsafe { options.open(tmp.path()) }?;
+  dq.extend(0..10);
+  dq.commit()?;
+  drop(dq);
+  assert!(read_metadata(tmp.path())?.little_endian);
+
+  // Rewrite the deque in the other byte order.
+  for path in chunk_files(tmp.path()) {
+    let bytes = fs::read(&path)?;
+    fs::write(&path, bytes.chunks(8).flat_map(|b| u64::from_ne_bytes(b.try_into().unwrap()).swap_bytes().to_ne_bytes()).collect::<Vec<_>>())?;
+  }
+  let values: Vec<u64> = (0..10).collect();
+  edit_metadata(tmp.path(), |meta| {
+    meta.little_endian = !meta.little_endian;
+    meta.pointer_width = 16;
+    meta.checksums = values.chunks(4).map(|c| crc32c::crc32c(&c.iter().flat_map(|v| v.swap_bytes().to_ne_bytes()).collect::<Vec<_>>())).collect();
+  })?;
+
+  assert!(matches!(unsafe { DequeOptions::new().open_read_only::<u64>(tmp.path()) }, Err(MmapVecDequeError::PlatformMismatch { .. })));
+  assert!(matches!(unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }, Err(MmapVecDequeError::PlatformMismatch { .. })));
+
+  // Portable readers swap the elements as they read them, leaving the files as they are.
+  let files = chunk_files(tmp.path());
+  let reader = unsafe { options.open_read_only::<u64>(tmp.path()) }?;
+  assert_eq!(reader.iter().collect::<Vec<_>>(), values);
+  assert_eq!(reader.iter().rev().collect::<Vec<_>>(), values.iter().rev().copied().collect::<Vec<_>>());
+  drop(reader);
+  let follower = unsafe { options.open_follower::<u64>(tmp.path()) }?;
+  assert_eq!(follower.get(3), Some(3));
+  drop(follower);
+  assert_eq!(chunk_files(tmp.path()), files);
+
+  let dq: MmapVecDeque<u64> = unsafe { options.open(tmp.path()) }?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), values);
+  drop(dq);
+  assert!(read_metadata(tmp.path())?.little_endian);
+  assert_ne!(chunk_files(tmp.path()), files);
+  let reader = unsafe { DequeOptions::new().open_read_only::<u64>(tmp.path()) }?;
+  assert_eq!(reader.iter().collect::<Vec<_>>(), values);
+  Ok(())
+}
+
+/// Test that deques that aren't portable only open on the platform that wrote them, and that the
+/// element alignment must match.
+#[test]
+fn test_platform_checks() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<[u8; 8]>::open_or_create(tmp.path(), None) }?;
+  dq.push_back([1; 8])?;
+  dq.commit()?;
+  drop(dq);
+
+  assert!(matches!(
+    unsafe { DequeOptions::new().accept_type_name("[u8; 8]").open::<u64>(tmp.path()) },
+    Err(MmapVecDequeError::AlignmentMismatch { stored: 1, requested: 8 })
+  ));
+
+  edit_metadata(tmp.path(), |meta| meta.pointer_width = 16)?;
+  assert!(matches!(unsafe { MmapVecDeque::<[u8; 8]>::open_or_create(tmp.path(), None) }, Err(MmapVecDequeError::PlatformMismatch { .. })));
+  // Declaring the element type portable on the wrong platform doesn't help.
+  assert!(matches!(unsafe { DequeOptions::new().portable::<[u8; 8]>().open::<[u8; 8]>(tmp.path()) }, Err(MmapVecDequeError::PlatformMismatch { .. })));
+  Ok(())
+}
+
+/// Test that a portable type of another size than the element type fails to open instead of
+/// converting the elements with it.
+#[test]
+fn test_portable_size_mismatch() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq: MmapVecDeque<u64> = unsafe { DequeOptions::new().chunk_size(4).portable::<u64>().open(tmp.path()) }?;
+  dq.extend(0..10);
+  dq.commit()?;
+  drop(dq);
+  edit_metadata(tmp.path(), |meta| meta.little_endian = !meta.little_endian)?;
+
+  let mut options = DequeOptions::new();
+  options.chunk_size(4).portable::<u32>();
+  assert!(matches!(unsafe { options.open::<u64>(tmp.path()) }, Err(MmapVecDequeError::Other(_))));
+  assert!(matches!(unsafe { options.open_read_only::<u64>(tmp.path()) }, Err(MmapVecDequeError::Other(_))));
+  assert!(matches!(unsafe { options.open_follower::<u64>(tmp.path()) }, Err(MmapVecDequeError::Other(_))));
+  assert!(matches!(unsafe { options.migrate(tmp.path(), |value: u64| value) }, Err(MmapVecDequeError::Other(_))));
+  Ok(())
+}
@@ -0,0 +1,82 @@
+use mmap_vecdeque::{DequeOptions, MmapVecDeque, MmapVecDequeError, Overflow};
+use std::fs;
+use tempfile::TempDir;
+
+/// Test that `disk_usage` counts metadata and every chunk file, orphaned ones included.
+#[test]
+fn test_disk_usage() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  // An empty deque keeps the chunk its next element goes to.
+  let metadata_len = fs::metadata(tmp.path().join("metadata.bin"))?.len();
+  assert_eq!(dq.disk_usage()?, metadata_len + 32);
+
+  dq.extend(0..10);
+  dq.commit()?;
+  let metadata_len = fs::metadata(tmp.path().join("metadata.bin"))?.len();
+  assert_eq!(dq.disk_usage()?, metadata_len + 3 * 32);
+
+  // An orphaned chunk file is counted until it is cleaned up; unrelated files never are.
+  fs::write(tmp.path().join("chunk_999.bin"), [0u8; 32])?;
+  fs::write(tmp.path().join("notes.txt"), [0u8; 100])?;
+  assert_eq!(dq.disk_usage()?, metadata_len + 4 * 32);
+  drop(dq);
+
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  assert_eq!(dq.disk_usage()?, metadata_len + 3 * 32);
+  Ok(())
+}
+
+/// Test that pushes needing a new chunk file fail once the quota is reached, leaving the deque
+/// unchanged, and succeed again after space is released.
+#[test]
+fn test_quota_exceeded() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq: MmapVecDeque<u64> = unsafe { DequeOptions::new().chunk_size(4).quota(1024).open(tmp.path()) }?;
+  assert_eq!(dq.quota(), Some(1024));
+
+  let mut pushed = 0;
+  let err = loop {
+    match dq.push_back(pushed) {
+      Ok(()) => pushed += 1,
+      Err(err) => break err,
+    }
+  };
+  assert!(matches!(err, MmapVecDequeError::QuotaExceeded { quota: 1024, needed } if needed > 1024));
+  assert!(dq.disk_usage()? <= 1024);
+  assert_eq!(dq.len() as u64, pushed);
+  assert_eq!(dq.back(), Some(pushed - 1));
+  assert!(matches!(dq.push_front(0), Err(MmapVecDequeError::QuotaExceeded { .. })));
+  assert!(matches!(dq.extend_back_from_slice(&[1, 2, 3, 4, 5]), Err(MmapVecDequeError::QuotaExceeded { .. })));
+  assert_eq!(dq.len() as u64, pushed);
+
+  // Popping whole chunks and committing deletes their files, making room again.
+  for _ in 0..32 {
+    dq.pop_front()?;
+  }
+  dq.commit()?;
+  dq.push_back(pushed)?;
+
+  dq.set_quota(None);
+  dq.extend(0..100);
+  assert!(dq.disk_usage()? > 1024);
+  Ok(())
+}
+
+/// Test that an insert into a full evicting deque that fails on the quota evicts nothing.
+#[test]
+fn test_quota_exceeded_on_evicting_insert() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq: MmapVecDeque<u64> = unsafe { DequeOptions::new().chunk_size(2).bounded(4, Overflow::Evict).open(tmp.path()) }?;
+  dq.extend([1, 2, 3, 4]);
+  dq.commit()?;
+  dq.set_quota(Some(dq.disk_usage()? + 8));
+
+  assert!(matches!(dq.push_back(9), Err(MmapVecDequeError::QuotaExceeded { .. })));
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+  assert!(matches!(dq.insert(4, 9), Err(MmapVecDequeError::QuotaExceeded { .. })));
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+  assert!(matches!(dq.insert(3, 9), Err(MmapVecDequeError::QuotaExceeded { .. })));
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+  Ok(())
+}
@@ -0,0 +1,68 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use tempfile::TempDir;
+
+/// Test that a read-only handle sees committed data and coexists only with other readers.
+#[test]
+fn test_read_only_open() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+
+  {
+    let mut dq = unsafe { MmapVecDeque::<u32>::open_or_create(path, Some(8)) }?;
+    for i in 0..20 {
+      dq.push_back(i)?;
+    }
+    dq.commit()?;
+  }
+
+  let reader = unsafe { MmapVecDeque::<u32>::open_read_only(path) }?;
+  let second = unsafe { MmapVecDeque::<u32>::open_read_only(path) }?;
+  assert_eq!(reader.len(), 20);
+  assert_eq!(reader.front(), Some(0));
+  assert_eq!(reader.back(), Some(19));
+  assert_eq!(second.get(5), Some(5));
+  assert_eq!(reader.get(20), None);
+  assert_eq!(reader.iter().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+
+  assert!(matches!(
+    unsafe { MmapVecDeque::<u32>::open_or_create(path, Some(8)) },
+    Err(MmapVecDequeError::Locked { .. })
+  ));
+  assert!(unsafe { MmapVecDeque::<u64>::open_read_only(path) }.is_err());
+
+  Ok(())
+}
+
+/// Test that opening a missing directory fails without creating anything.
+#[test]
+fn test_read_only_missing_directory() {
+  let tmp = TempDir::new().unwrap();
+  let path = tmp.path().join("missing");
+
+  assert!(unsafe { MmapVecDeque::<u32>::open_read_only(&path) }.is_err());
+  assert!(!path.exists());
+}
+
+/// Test that a reader shows the committed state left by a writer that died mid-transaction.
+#[test]
+fn test_read_only_after_crash() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+
+  {
+    let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(4)) }?;
+    for i in 0..10 {
+      dq.push_back(i)?;
+    }
+    dq.commit()?;
+    for val in dq.iter_mut() {
+      *val *= 100;
+    }
+  }
+
+  let reader = unsafe { MmapVecDeque::<u64>::open_read_only(path) }?;
+  assert_eq!(reader.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+  assert!(path.join("journal.bin").exists());
+
+  Ok(())
+}
@@ -0,0 +1,189 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError, Reader};
+use std::thread;
+use tempfile::TempDir;
+
+/// Test that readers only see committed elements and move forward on refresh.
+#[test]
+fn test_reader_sees_committed_state() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  for i in 0..10 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+
+  let mut reader = dq.reader()?;
+  let snapshot = reader.clone();
+
+  // Pending changes, including in-place overwrites, stay invisible.
+  dq.pop_back()?;
+  dq.push_back(900)?;
+  dq.push_back(901)?;
+  for val in dq.iter_mut() {
+    *val += 1000;
+  }
+  assert!(!reader.refresh()?);
+  assert_eq!(reader.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+
+  dq.commit()?;
+  assert_eq!(snapshot.len(), 10);
+  assert!(reader.refresh()?);
+  assert_eq!(reader.len(), 11);
+  assert_eq!(reader.front(), Some(1000));
+  assert_eq!(reader.back(), Some(1901));
+
+  // Rolled back changes are never seen either.
+  dq.push_front(5)?;
+  for val in dq.iter_mut() {
+    *val = 0;
+  }
+  assert_eq!(reader.get(1), Some(1001));
+  dq.rollback()?;
+  assert_eq!(reader.get(1), Some(1001));
+  assert!(!reader.refresh()?);
+
+  Ok(())
+}
+
+/// Test that a snapshot keeps its range while later commits pop and push over it.
+#[test]
+fn test_old_snapshot_survives_released_chunks() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  for i in 0..12 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+  let old = dq.reader()?;
+
+  for _ in 0..10 {
+    dq.pop_front()?;
+  }
+  for _ in 0..2 {
+    dq.pop_back()?;
+  }
+  dq.commit()?;
+  for i in 0..6 {
+    dq.push_back(100 + i)?;
+  }
+
+  assert_eq!(old.iter().collect::<Vec<_>>(), (0..12).collect::<Vec<_>>());
+  let fresh = dq.reader()?;
+  assert!(fresh.is_empty());
+
+  Ok(())
+}
+
+/// Test a reader thread scanning while the writer keeps appending and committing.
+#[test]
+fn test_reader_on_another_thread() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(16)) }?;
+  dq.commit()?;
+  let mut reader: Reader<u64> = dq.reader()?;
+
+  let handle = thread::spawn(move || -> Result<(), MmapVecDequeError> {
+    let mut seen = 0;
+    while seen < 500 {
+      reader.refresh()?;
+      let values: Vec<u64> = reader.iter().collect();
+      assert_eq!(values, (0..values.len() as u64).collect::<Vec<_>>());
+      assert!(values.len() >= seen);
+      seen = values.len();
+    }
+    Ok(())
+  });
+
+  for i in 0..500 {
+    dq.push_back(i)?;
+    dq.push_back(u64::MAX)?;
+    dq.pop_back()?;
+    dq.commit()?;
+  }
+  handle.join().unwrap()?;
+
+  Ok(())
+}
+
+/// Test that a reader keeps showing its commit while several later commits overwrite it in place.
+#[test]
+fn test_stale_reader_keeps_its_commit() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  for i in 0..5 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+  let stale = dq.reader()?;
+
+  dq.pop_front()?;
+  dq.push_front(1000)?;
+  dq.set(2, 777)?;
+  dq.commit()?;
+  let middle = dq.reader()?;
+  dq.set(1, 555)?;
+  dq.set(2, 778)?;
+  dq.commit()?;
+
+  assert_eq!(stale.iter().collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+  assert_eq!(stale.iter().rev().collect::<Vec<_>>(), vec![4, 3, 2, 1, 0]);
+  assert_eq!(middle.iter().collect::<Vec<_>>(), vec![1000, 1, 777, 3, 4]);
+  assert_eq!(dq.reader()?.iter().collect::<Vec<_>>(), vec![1000, 555, 778, 3, 4]);
+
+  let mut refreshed = stale.clone();
+  drop(stale);
+  assert!(refreshed.refresh()?);
+  drop(middle);
+  dq.set(0, 1)?;
+  dq.commit()?;
+  assert_eq!(refreshed.iter().collect::<Vec<_>>(), vec![1000, 555, 778, 3, 4]);
+  Ok(())
+}
+
+/// Test that rolling back writes over slots an older reader still shows keeps that reader intact.
+#[test]
+fn test_reader_survives_rollback() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  dq.extend([1, 2, 3]);
+  dq.commit()?;
+  let reader = dq.reader()?;
+
+  dq.pop_back()?;
+  dq.commit()?;
+  dq.push_back(99)?;
+  dq.set(0, 100)?;
+  dq.rollback()?;
+  assert_eq!(reader.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![1, 2]);
+
+  dq.push_back(98)?;
+  dq.commit()?;
+  assert_eq!(reader.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+  assert_eq!(dq.reader()?.iter().collect::<Vec<_>>(), vec![1, 2, 98]);
+  Ok(())
+}
+
+/// Test that a reader outliving its deque keeps the directory locked, so no other writer can
+/// change the elements it shows.
+#[test]
+fn test_reader_keeps_directory_locked() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  dq.extend([1, 2, 3]);
+  dq.commit()?;
+  let reader = dq.reader()?;
+  drop(dq);
+
+  let reopened = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) };
+  assert!(matches!(reopened, Err(MmapVecDequeError::Locked { .. })));
+  assert_eq!(reader.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+  drop(reader);
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(tmp.path(), Some(4)) }?;
+  dq.pop_back()?;
+  dq.push_back(99)?;
+  dq.commit()?;
+  assert_eq!(dq.iter().collect::<Vec<_>>(), vec![1, 2, 99]);
+  Ok(())
+}
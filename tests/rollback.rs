@@ -0,0 +1,67 @@
+use mmap_vecdeque::{MmapVecDeque, MmapVecDequeError};
+use tempfile::TempDir;
+
+/// Test that rollback discards pushes and pops made since the last commit.
+#[test]
+fn test_rollback_discards_pending_changes() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+  let mut dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(4)) }?;
+
+  for i in 0..10 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+
+  dq.pop_front()?;
+  dq.pop_front()?;
+  for i in 100..110 {
+    dq.push_front(i)?;
+    dq.push_back(i)?;
+  }
+  assert_eq!(dq.len(), 28);
+
+  dq.rollback()?;
+  assert_eq!(dq.len(), 10);
+  let collected: Vec<u64> = dq.iter().collect();
+  assert_eq!(collected, (0..10).collect::<Vec<_>>());
+
+  // The deque stays usable after a rollback.
+  dq.push_back(10)?;
+  dq.commit()?;
+  drop(dq);
+
+  let dq = unsafe { MmapVecDeque::<u64>::open_or_create(path, Some(4)) }?;
+  let collected: Vec<u64> = dq.iter().collect();
+  assert_eq!(collected, (0..11).collect::<Vec<_>>());
+
+  Ok(())
+}
+
+/// Test that rollback restores committed slots that were overwritten in place.
+#[test]
+fn test_rollback_restores_overwritten_elements() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let path = tmp.path();
+  let mut dq = unsafe { MmapVecDeque::<u32>::open_or_create(path, Some(8)) }?;
+
+  for i in 0..20 {
+    dq.push_back(i)?;
+  }
+  dq.commit()?;
+
+  // pop_back followed by push_back reuses the committed slot.
+  dq.pop_back()?;
+  dq.push_back(999)?;
+  for val in dq.iter_mut() {
+    *val += 1000;
+  }
+  dq.clear()?;
+  dq.push_back(7)?;
+
+  dq.rollback()?;
+  let collected: Vec<u32> = dq.iter().collect();
+  assert_eq!(collected, (0..20).collect::<Vec<_>>());
+
+  Ok(())
+}
@@ -0,0 +1,84 @@
+use mmap_vecdeque::{MmapSerdeDeque, MmapVecDequeError};
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+
+mod common;
+
+use common::segment_files;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Event {
+  id: u64,
+  name: String,
+  tags: Vec<String>,
+}
+
+fn event(id: u64) -> Event {
+  Event { id, name: format!("event-{}", id), tags: (0..id % 4).map(|t| t.to_string()).collect() }
+}
+
+/// Test push/pop at both ends, random access, iteration, commit and rollback with heap-allocated
+/// elements.
+#[test]
+fn test_serde_deque_basic() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = MmapSerdeDeque::<Event>::open_or_create(tmp.path(), Some(4))?;
+  for id in 0..10 {
+    dq.push_back(event(id))?;
+  }
+  dq.push_front(event(100))?;
+  dq.commit()?;
+
+  assert_eq!(dq.len(), 11);
+  assert_eq!(dq.front()?, Some(event(100)));
+  assert_eq!(dq.back()?, Some(event(9)));
+  assert_eq!(dq.get(3)?, Some(event(2)));
+  assert_eq!(dq.get(11)?, None);
+  let expected: Vec<Event> = std::iter::once(100).chain(0..10).map(event).collect();
+  assert_eq!(dq.iter().collect::<Result<Vec<_>, _>>()?, expected);
+  assert_eq!(dq.iter().next_back().transpose()?, Some(event(9)));
+
+  assert_eq!(dq.pop_front()?, Some(event(100)));
+  assert_eq!(dq.pop_back()?, Some(event(9)));
+  dq.push_back(event(200))?;
+  dq.rollback()?;
+  assert_eq!(dq.iter().collect::<Result<Vec<_>, _>>()?, expected);
+
+  dq.pop_front()?;
+  dq.commit()?;
+  drop(dq);
+
+  let mut dq = MmapSerdeDeque::<Event>::open_or_create(tmp.path(), Some(4))?;
+  assert_eq!(dq.iter().collect::<Result<Vec<_>, _>>()?, expected[1..].to_vec());
+  dq.clear()?;
+  assert_eq!(dq.pop_front()?, None);
+  Ok(())
+}
+
+/// Test that segments are deleted once no committed element refers to them, and that records of
+/// uncommitted pushes are ignored after a crash.
+#[test]
+fn test_segments_are_released() -> Result<(), MmapVecDequeError> {
+  let tmp = TempDir::new()?;
+  let mut dq = MmapSerdeDeque::<String>::open_or_create(tmp.path(), None)?;
+  let payload = "x".repeat(100_000);
+  for _ in 0..40 {
+    dq.push_back(payload.clone())?;
+  }
+  dq.commit()?;
+  assert!(segment_files(tmp.path()).len() >= 4);
+
+  for _ in 0..25 {
+    dq.pop_front()?;
+  }
+  dq.commit()?;
+  assert_eq!(segment_files(tmp.path()).len(), 2);
+  assert_eq!(dq.len(), 15);
+
+  dq.push_back("uncommitted".to_string())?;
+  drop(dq);
+  let dq = MmapSerdeDeque::<String>::open_or_create(tmp.path(), None)?;
+  assert_eq!(dq.len(), 15);
+  assert!(dq.iter().all(|value| value.unwrap() == payload));
+  Ok(())
+}